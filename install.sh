@@ -31,6 +31,17 @@ echo "✓ Installed desktop file"
 install -D -m 0644 assets/com.cpupowermanager.policy /usr/share/polkit-1/actions/com.cpupowermanager.policy
 echo "✓ Installed PolicyKit policy"
 
+# power-profiles-daemon ships the same actions when it is installed
+if [ ! -f /usr/share/polkit-1/actions/power-profiles-daemon.policy ]; then
+    install -D -m 0644 assets/org.freedesktop.UPower.PowerProfiles.policy \
+        /usr/share/polkit-1/actions/org.freedesktop.UPower.PowerProfiles.policy
+    echo "✓ Installed power profiles PolicyKit policy"
+fi
+
+# Install D-Bus system bus policy for the background service
+install -D -m 0644 assets/com.cpupowermanager.Daemon.conf /usr/share/dbus-1/system.d/com.cpupowermanager.Daemon.conf
+echo "✓ Installed D-Bus policy"
+
 # Install icon
 install -D -m 0644 assets/icon.svg /usr/share/icons/hicolor/scalable/apps/cpu-power-manager.svg
 echo "✓ Installed icon"
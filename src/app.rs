@@ -1,20 +1,36 @@
 use gtk4::prelude::*;
 use gtk4::{glib, Application, ApplicationWindow, Box, Button, HeaderBar, Label, Orientation, Switch, ComboBoxText, Grid, ScrolledWindow, Frame};
-use crate::backend::CpuManager;
+use crate::backend::{CpuManager, EnergySampler, PowerManager, TemperatureController, ThermalProtector, ThrottleMonitor};
+use crate::backend::power::BatteryEstimator;
 use crate::backend::thermal::ThermalManager;
-use crate::backend::profile::ProfileManager;
+use crate::backend::thermal_protection::Intervention;
 use crate::config::ConfigManager;
-use std::sync::{Arc, Mutex};
+use crate::system::controller::{Access, Controller};
+use crate::system::events::ServiceEvent;
+use crate::utils::temperature::TemperatureUnit;
+use notify_rust::{Notification, Urgency};
+use std::sync::{mpsc, Arc, Mutex};
 
 pub struct AppWindow {
     window: ApplicationWindow,
     cpu_manager: Arc<Mutex<CpuManager>>,
     thermal_manager: Arc<Mutex<ThermalManager>>,
-    profile_manager: Arc<Mutex<ProfileManager>>,
+    // Settings are changed through this, so the GUI itself needs no root
+    controller: Arc<dyn Controller>,
+    access: Access,
     config_manager: Arc<Mutex<ConfigManager>>,
+    energy_sampler: Arc<Mutex<Option<EnergySampler>>>,
+    power_manager: Arc<Mutex<PowerManager>>,
+    battery_estimator: Arc<Mutex<BatteryEstimator>>,
+    thermal_protector: Arc<Mutex<ThermalProtector>>,
+    temperature_controller: Arc<Mutex<Option<TemperatureController>>>,
+    throttle_monitor: Arc<Mutex<ThrottleMonitor>>,
     // UI elements we need to update
     freq_label: Label,
     temp_label: Label,
+    power_label: Label,
+    battery_label: Label,
+    throttle_label: Label,
     governor_label: Label,
     turbo_label: Label,
     per_core_box: Box,
@@ -28,10 +44,33 @@ impl AppWindow {
         let thermal_manager = Arc::new(Mutex::new(
             ThermalManager::new().expect("Failed to initialize thermal manager")
         ));
-        let profile_manager = Arc::new(Mutex::new(ProfileManager::new()));
+        let (controller, access) = crate::system::controller::open()
+            .expect("Failed to initialize CPU controller");
+        let controller: Arc<dyn Controller> = Arc::from(controller);
+        log::info!("Settings access: {:?}", access);
         let config_manager = Arc::new(Mutex::new(
             ConfigManager::new().expect("Failed to initialize config manager")
         ));
+        thermal_manager.lock().unwrap().set_cpu_temp_source(
+            config_manager.lock().unwrap().get_config().thermal.cpu_temp_source.clone()
+        );
+        let energy_sampler = match EnergySampler::new() {
+            Ok(sampler) => Some(sampler),
+            Err(e) => {
+                log::warn!("Package power readout unavailable: {}", e);
+                None
+            }
+        };
+        let energy_sampler = Arc::new(Mutex::new(energy_sampler));
+        let power_manager = Arc::new(Mutex::new(
+            PowerManager::new().expect("Failed to initialize power manager")
+        ));
+        let battery_estimator = Arc::new(Mutex::new(BatteryEstimator::new()));
+        let thermal_protector = Arc::new(Mutex::new(ThermalProtector::new(
+            config_manager.lock().unwrap().get_config().thermal.clone()
+        )));
+        let temperature_controller = Arc::new(Mutex::new(None));
+        let throttle_monitor = Arc::new(Mutex::new(ThrottleMonitor::new()));
 
         let window = ApplicationWindow::builder()
             .application(app)
@@ -42,7 +81,11 @@ impl AppWindow {
 
         // Create labels that we'll update
         let freq_label = Label::new(Some("-- MHz"));
-        let temp_label = Label::new(Some("--°C"));
+        let unit = config_manager.lock().unwrap().get_config().general.temperature_unit;
+        let temp_label = Label::new(Some(&format!("--{}", unit.symbol())));
+        let power_label = Label::new(Some("-- W"));
+        let battery_label = Label::new(Some("--"));
+        let throttle_label = Label::new(Some("None"));
         let governor_label = Label::new(Some("--"));
         let turbo_label = Label::new(Some("--"));
         let per_core_box = Box::new(Orientation::Vertical, 4);
@@ -51,10 +94,20 @@ impl AppWindow {
             window,
             cpu_manager,
             thermal_manager,
-            profile_manager,
+            controller,
+            access,
             config_manager,
+            energy_sampler,
+            power_manager,
+            battery_estimator,
+            thermal_protector,
+            temperature_controller,
+            throttle_monitor,
             freq_label,
             temp_label,
+            power_label,
+            battery_label,
+            throttle_label,
             governor_label,
             turbo_label,
             per_core_box,
@@ -92,16 +145,28 @@ impl AppWindow {
         main_box.set_margin_start(12);
         main_box.set_margin_end(12);
 
+        if !self.access.can_write() {
+            let banner = Label::new(Some(
+                "Read-only mode: start the background service (sudo systemctl start cpu-power-manager) \
+                 or run as root to change settings."
+            ));
+            banner.add_css_class("readonly-banner");
+            banner.set_wrap(true);
+            main_box.append(&banner);
+        }
+
         // Dashboard section
         let dashboard = self.create_dashboard();
         main_box.append(&dashboard);
 
         // Quick Profile buttons
         let profiles_box = self.create_profile_buttons();
+        profiles_box.set_sensitive(self.access.can_write());
         main_box.append(&profiles_box);
 
         // Advanced Controls section
         let controls = self.create_advanced_controls();
+        controls.set_sensitive(self.access.can_write());
         main_box.append(&controls);
 
         // Per-Core Status section
@@ -165,6 +230,37 @@ impl AppWindow {
 
         dashboard.append(&temp_box);
 
+        // Power Info
+        let power_box = Box::new(Orientation::Vertical, 8);
+        let power_title = Label::new(Some("Package Power"));
+        power_title.add_css_class("title");
+        power_box.append(&power_title);
+
+        power_box.append(&self.power_label);
+
+        dashboard.append(&power_box);
+
+        // Battery Info
+        let battery_box = Box::new(Orientation::Vertical, 8);
+        let battery_title = Label::new(Some("Battery"));
+        battery_title.add_css_class("title");
+        battery_box.append(&battery_title);
+
+        battery_box.append(&self.battery_label);
+
+        dashboard.append(&battery_box);
+
+        // Throttling Info
+        let throttle_box = Box::new(Orientation::Vertical, 8);
+        let throttle_title = Label::new(Some("Throttling"));
+        throttle_title.add_css_class("title");
+        throttle_box.append(&throttle_title);
+
+        self.throttle_label.set_wrap(true);
+        throttle_box.append(&self.throttle_label);
+
+        dashboard.append(&throttle_box);
+
         // Governor Info
         let gov_box = Box::new(Orientation::Vertical, 8);
         let gov_title = Label::new(Some("Current Governor"));
@@ -198,21 +294,45 @@ impl AppWindow {
         let profiles_box = Box::new(Orientation::Horizontal, 8);
         profiles_box.set_halign(gtk4::Align::Center);
 
-        let profile_manager = self.profile_manager.lock().unwrap();
-        for profile in profile_manager.get_profiles() {
-            let button = Button::with_label(&profile.name);
-            button.set_tooltip_text(Some(&profile.description));
+        let profile_names = self.controller.list_profiles().unwrap_or_else(|e| {
+            log::error!("Failed to list profiles: {}", e);
+            vec![]
+        });
+        for profile_name in profile_names {
+            // Display name and description come from the local config; the service may know profiles we don't
+            let profile = self.config_manager.lock().unwrap().get_profile(&profile_name).ok();
+            let label = profile.as_ref().map_or(profile_name.clone(), |p| p.name.clone());
+            let button = Button::with_label(&label);
+            if let Some(profile) = &profile {
+                button.set_tooltip_text(Some(&profile.description));
+            }
             
+            let controller = self.controller.clone();
+            let access = self.access;
             let cpu_manager = self.cpu_manager.clone();
-            let profile_clone = profile.clone();
+            let config_manager = self.config_manager.clone();
+            let thermal_protector = self.thermal_protector.clone();
+            let temperature_controller = self.temperature_controller.clone();
             button.connect_clicked(move |btn| {
-                let cpu_manager = cpu_manager.lock().unwrap();
-                match profile_clone.apply(&cpu_manager) {
+                match controller.apply_profile(&profile_name) {
                     Ok(_) => {
-                        btn.set_label(&format!("✓ {}", profile_clone.name));
+                        // The service runs thermal protection and temperature control itself
+                        if access == Access::Direct {
+                            let cpu_manager = cpu_manager.lock().unwrap();
+                            thermal_protector.lock().unwrap().reset();
+                            let target = profile.as_ref().and_then(|p| p.target_temp_celsius);
+                            let max_freq_mhz = profile.as_ref().and_then(|p| p.max_freq_mhz);
+                            *temperature_controller.lock().unwrap() = target.and_then(|target| {
+                                let gains = config_manager.lock().unwrap().get_config().thermal.pid.clone();
+                                TemperatureController::new(target, max_freq_mhz, gains, &cpu_manager)
+                                    .map_err(|e| log::warn!("Failed to start temperature control: {}", e))
+                                    .ok()
+                            });
+                        }
+                        btn.set_label(&format!("✓ {}", label));
                         // Reset label after 2 seconds
                         let btn_clone = btn.clone();
-                        let name = profile_clone.name.clone();
+                        let name = label.clone();
                         glib::timeout_add_seconds_local(2, move || {
                             btn_clone.set_label(&name);
                             glib::ControlFlow::Break
@@ -220,9 +340,9 @@ impl AppWindow {
                     }
                     Err(e) => {
                         log::error!("Failed to apply profile: {}", e);
-                        btn.set_label(&format!("✗ {}", profile_clone.name));
+                        btn.set_label(&format!("✗ {}", label));
                         let btn_clone = btn.clone();
-                        let name = profile_clone.name.clone();
+                        let name = label.clone();
                         glib::timeout_add_seconds_local(2, move || {
                             btn_clone.set_label(&name);
                             glib::ControlFlow::Break
@@ -246,6 +366,7 @@ impl AppWindow {
         max_freq_button.set_tooltip_text(Some("Automatically detect and set to your CPU's maximum hardware frequency"));
         
         let cpu_manager_clone = self.cpu_manager.clone();
+        let controller = self.controller.clone();
         max_freq_button.connect_clicked(move |btn| {
             let cpu_manager = cpu_manager_clone.lock().unwrap();
             
@@ -275,27 +396,30 @@ impl AppWindow {
 
                     // Reset all limits to full hardware range (removes any caps)
                     log::info!("Resetting frequency range to hardware limits: {}-{} MHz", hw_min, max_freq);
-                    for core in 0..cpu_manager.core_count() {
-                        if let Err(e) = cpu_manager.set_scaling_min_freq(core, hw_min) {
-                            log::warn!("Failed to reset min freq for core {}: {}", core, e);
-                        }
-                        if let Err(e) = cpu_manager.set_scaling_max_freq(core, max_freq) {
-                            log::warn!("Failed to set max freq for core {}: {}", core, e);
-                        }
+                    let mut failed = false;
+                    if let Err(e) = controller.set_frequency_limits(hw_min, max_freq) {
+                        log::warn!("Failed to reset frequency limits: {}", e);
+                        failed = true;
                     }
 
                     // Set performance governor for best speed
-                    if let Err(e) = cpu_manager.set_governor_all("performance") {
+                    if let Err(e) = controller.set_governor("performance") {
                         log::warn!("Failed to set performance governor: {}", e);
+                        failed = true;
                     }
 
                     // Enable turbo boost
-                    if let Err(e) = cpu_manager.set_turbo(true) {
+                    if let Err(e) = controller.set_turbo(true) {
                         log::warn!("Failed to enable turbo: {}", e);
+                        failed = true;
                     }
 
-                    btn.set_label(&format!("✓ Set to {} MHz", max_freq));
-                    log::info!("Successfully configured all cores for maximum frequency: {} MHz", max_freq);
+                    if failed {
+                        btn.set_label("✗ Failed to apply");
+                    } else {
+                        btn.set_label(&format!("✓ Set to {} MHz", max_freq));
+                        log::info!("Successfully configured all cores for maximum frequency: {} MHz", max_freq);
+                    }
                     
                     // Reset button label after 3 seconds
                     let btn_clone = btn.clone();
@@ -353,11 +477,10 @@ impl AppWindow {
             }
         }
 
-        let cpu_mgr_clone = self.cpu_manager.clone();
+        let controller = self.controller.clone();
         governor_combo.connect_changed(move |combo| {
             if let Some(governor) = combo.active_text() {
-                let cpu_manager = cpu_mgr_clone.lock().unwrap();
-                if let Err(e) = cpu_manager.set_governor_all(&governor) {
+                if let Err(e) = controller.set_governor(&governor) {
                     log::error!("Failed to set governor: {}", e);
                 }
             }
@@ -374,10 +497,9 @@ impl AppWindow {
             turbo_switch.set_active(enabled);
         }
 
-        let cpu_mgr_clone = self.cpu_manager.clone();
+        let controller = self.controller.clone();
         turbo_switch.connect_state_set(move |_, state| {
-            let cpu_manager = cpu_mgr_clone.lock().unwrap();
-            if let Err(e) = cpu_manager.set_turbo(state) {
+            if let Err(e) = controller.set_turbo(state) {
                 log::error!("Failed to set turbo: {}", e);
             }
             glib::Propagation::Proceed
@@ -385,7 +507,11 @@ impl AppWindow {
         grid.attach(&turbo_switch, 1, 1, 1, 1);
 
         // Info label
-        let info_label = Label::new(Some("Note: Changes require root privileges. Run with sudo or configure PolicyKit."));
+        let info_label = Label::new(Some(match self.access {
+            Access::Daemon => "Changes are applied by the cpu-power-manager service.",
+            Access::Direct => "Running as root: changes are written directly.",
+            Access::ReadOnly => "Read-only: start the cpu-power-manager service or run as root to change settings.",
+        }));
         info_label.add_css_class("subtitle");
         info_label.set_wrap(true);
         info_label.set_max_width_chars(60);
@@ -431,14 +557,46 @@ impl AppWindow {
         // Setup periodic UI updates every second
         let freq_label = self.freq_label.clone();
         let temp_label = self.temp_label.clone();
+        let power_label = self.power_label.clone();
+        let battery_label = self.battery_label.clone();
+        let throttle_label = self.throttle_label.clone();
         let governor_label = self.governor_label.clone();
         let turbo_label = self.turbo_label.clone();
         let cpu_manager = self.cpu_manager.clone();
         let thermal_manager = self.thermal_manager.clone();
+        let energy_sampler = self.energy_sampler.clone();
+        let power_manager = self.power_manager.clone();
+        let battery_estimator = self.battery_estimator.clone();
+        let thermal_protector = self.thermal_protector.clone();
+        let temperature_controller = self.temperature_controller.clone();
+        let throttle_monitor = self.throttle_monitor.clone();
+        let access = self.access;
         let per_core_box = self.per_core_box.clone();
         let cpu_mgr_clone = self.cpu_manager.clone();
+        let unit = self.config_manager.lock().unwrap().get_config().general.temperature_unit;
+
+        // The service enforces thermal limits itself but can't reach the desktop session
+        let (emergency_sender, emergencies) = mpsc::channel();
+        if access == Access::Daemon {
+            std::thread::spawn(move || match crate::system::controller::subscribe() {
+                Ok(events) => {
+                    for event in events {
+                        if let ServiceEvent::ThermalEmergency { temp_celsius, max_freq_mhz } = event
+                            && emergency_sender.send((temp_celsius, max_freq_mhz)).is_err()
+                        {
+                            break;
+                        }
+                    }
+                }
+                Err(e) => log::warn!("Not following service events: {}", e),
+            });
+        }
 
         glib::timeout_add_seconds_local(1, move || {
+            while let Ok((temp, max_freq_mhz)) = emergencies.try_recv() {
+                notify_thermal_emergency(temp, max_freq_mhz, unit);
+            }
+
             // Update frequency
             let cpu_mgr = cpu_manager.lock().unwrap();
             if let Ok(freqs) = cpu_mgr.get_all_frequencies() {
@@ -449,7 +607,7 @@ impl AppWindow {
             // Update temperature
             let thermal_mgr = thermal_manager.lock().unwrap();
             if let Ok(temp) = thermal_mgr.get_cpu_temperature() {
-                temp_label.set_text(&format!("{:.1}°C", temp));
+                temp_label.set_text(&unit.format(temp, 1));
                 
                 // Update CSS class based on temperature
                 temp_label.remove_css_class("temp-normal");
@@ -466,6 +624,62 @@ impl AppWindow {
                 } else {
                     temp_label.add_css_class("temp-critical");
                 }
+
+                // With the service running it enforces limits itself; read-only mode can't
+                if access == Access::Direct {
+                    let mut protector = thermal_protector.lock().unwrap();
+                    match protector.update(temp, &cpu_mgr) {
+                        Ok(Some(Intervention::Emergency(max_freq_mhz))) => {
+                            notify_thermal_emergency(temp, max_freq_mhz, unit);
+                        }
+                        Ok(_) => {}
+                        Err(e) => log::error!("Thermal protection failed: {}", e),
+                    }
+                    if !protector.is_active()
+                        && let Some(controller) = temperature_controller.lock().unwrap().as_mut()
+                        && let Err(e) = controller.update(temp, &cpu_mgr)
+                    {
+                        log::error!("Temperature control failed: {}", e);
+                    }
+                }
+            }
+
+            // Update throttling
+            let mut monitor = throttle_monitor.lock().unwrap();
+            monitor.poll(&cpu_mgr, &thermal_mgr);
+            if let Some(event) = monitor.last_event() {
+                let counts = monitor.counts();
+                let total = counts.core_thermal + counts.package_thermal + counts.frequency_limited + counts.cooling_device;
+                throttle_label.set_text(&format!("{} events\nLast {}: {}", total, event.timestamp.format("%H:%M:%S"), event.cause));
+            }
+
+            // Update package power
+            if let Some(sampler) = energy_sampler.lock().unwrap().as_mut() {
+                match sampler.sample() {
+                    Ok(Some(zones)) => {
+                        if let Some(watts) = EnergySampler::package_watts(&zones) {
+                            power_label.set_text(&format!("{:.1} W", watts));
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => log::debug!("Failed to sample package power: {}", e),
+                }
+            }
+
+            // Update battery
+            if let Ok(batteries) = power_manager.lock().unwrap().get_batteries() {
+                let estimate = battery_estimator.lock().unwrap().update(&batteries);
+                let capacity = batteries.iter().find_map(|b| b.capacity_percent);
+
+                let text = match (capacity, estimate.discharge_watts, estimate.format_time_remaining()) {
+                    (None, _, _) => "No battery".to_string(),
+                    (Some(capacity), Some(watts), Some(remaining)) => {
+                        format!("{}% · {:.1} W · {}", capacity, watts, remaining)
+                    }
+                    (Some(capacity), Some(watts), None) => format!("{}% · {:.1} W", capacity, watts),
+                    (Some(capacity), None, _) => format!("{}% · {}", capacity, batteries[0].status),
+                };
+                battery_label.set_text(&text);
             }
 
             // Update governor
@@ -511,6 +725,11 @@ impl AppWindow {
 
                     core_box.append(&core_label);
                     core_box.append(&freq_label);
+                    if let Some(temp) = status.temperature_celsius {
+                        let temp_label = Label::new(Some(&unit.format(temp, 0)));
+                        temp_label.add_css_class("value");
+                        core_box.append(&temp_label);
+                    }
                     core_box.append(&gov_label);
 
                     per_core_box.append(&core_box);
@@ -525,3 +744,20 @@ impl AppWindow {
         self.window.present();
     }
 }
+
+fn notify_thermal_emergency(temp_celsius: f32, max_freq_mhz: u32, unit: TemperatureUnit) {
+    let result = Notification::new()
+        .summary("CPU temperature critical")
+        .body(&format!(
+            "CPU reached {}. Frequency limited to {} MHz until it cools down.",
+            unit.format(temp_celsius, 1),
+            max_freq_mhz
+        ))
+        .icon("cpu-power-manager")
+        .urgency(Urgency::Critical)
+        .show();
+
+    if let Err(e) = result {
+        log::warn!("Failed to show thermal emergency notification: {}", e);
+    }
+}
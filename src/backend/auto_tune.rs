@@ -1,10 +1,69 @@
 // Auto-tuning engine module
 use anyhow::Result;
 
-pub struct AutoTuner;
+use crate::config::AutoTuneConfig;
+
+/// Picks the profile for the current power source. A profile chosen manually
+/// by the user stays in effect until the power source changes again.
+pub struct AutoTuner {
+    on_ac_power: Option<bool>,
+    manual_override: Option<String>,
+}
 
 impl AutoTuner {
     pub fn new() -> Result<Self> {
-        Ok(Self)
+        Ok(Self {
+            on_ac_power: None,
+            manual_override: None,
+        })
+    }
+
+    /// Record the current power source. Returns the profile to switch to when
+    /// the source differs from the last one seen.
+    pub fn update_power_source(&mut self, on_ac: bool, config: &AutoTuneConfig) -> Option<String> {
+        if self.on_ac_power == Some(on_ac) {
+            return None;
+        }
+        self.on_ac_power = Some(on_ac);
+
+        if let Some(profile) = self.manual_override.take() {
+            log::info!("Power source changed, ending manual override of profile '{}'", profile);
+        }
+
+        Some(if on_ac { config.ac_profile.clone() } else { config.battery_profile.clone() })
+    }
+
+    pub fn set_manual_override(&mut self, profile: &str) {
+        log::info!("Profile '{}' selected manually, holding it until the next power source change", profile);
+        self.manual_override = Some(profile.to_string());
+    }
+
+    pub fn manual_override(&self) -> Option<&str> {
+        self.manual_override.as_deref()
+    }
+
+    pub fn on_ac_power(&self) -> Option<bool> {
+        self.on_ac_power
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manual_override_until_power_change() {
+        let config = AutoTuneConfig::default();
+        let mut tuner = AutoTuner::new().unwrap();
+
+        assert_eq!(tuner.update_power_source(true, &config).as_deref(), Some("performance"));
+        tuner.set_manual_override("silent");
+
+        // Spurious events for the same source keep the override
+        assert_eq!(tuner.update_power_source(true, &config), None);
+        assert_eq!(tuner.manual_override(), Some("silent"));
+
+        assert_eq!(tuner.update_power_source(false, &config).as_deref(), Some("balanced"));
+        assert_eq!(tuner.manual_override(), None);
     }
 }
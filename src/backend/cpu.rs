@@ -2,10 +2,11 @@ use anyhow::{Context, Result};
 use std::fs;
 use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::backend::HwmonManager;
 
 const CPUFREQ_BASE: &str = "/sys/devices/system/cpu";
-const INTEL_PSTATE_PATH: &str = "/sys/devices/system/cpu/intel_pstate";
-const AMD_PSTATE_PATH: &str = "/sys/devices/system/cpu/amd_pstate";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CpuInfo {
@@ -36,30 +37,34 @@ pub struct CoreStatus {
     pub governor: String,
     pub online: bool,
     pub usage_percent: f32,
+    pub temperature_celsius: Option<f32>,
 }
 
 pub struct CpuManager {
     core_count: usize,
     driver: CpuDriver,
     base_path: PathBuf,
+    hwmon: HwmonManager,
 }
 
 impl CpuManager {
     pub fn new() -> Result<Self> {
-        let core_count = Self::detect_core_count()?;
-        let driver = Self::detect_driver();
-        
-        log::info!("Detected {} CPU cores with {:?} driver", core_count, driver);
-        
+        let manager = Self::from_base(Path::new(CPUFREQ_BASE))?;
+        log::info!("Detected {} CPU cores with {:?} driver", manager.core_count, manager.driver);
+        Ok(manager)
+    }
+
+    pub(crate) fn from_base(base: &Path) -> Result<Self> {
         Ok(Self {
-            core_count,
-            driver,
-            base_path: PathBuf::from(CPUFREQ_BASE),
+            core_count: Self::detect_core_count(base)?,
+            driver: Self::detect_driver(base),
+            base_path: base.to_path_buf(),
+            hwmon: HwmonManager::new()?,
         })
     }
 
-    fn detect_core_count() -> Result<usize> {
-        let entries = fs::read_dir(CPUFREQ_BASE)
+    fn detect_core_count(base: &Path) -> Result<usize> {
+        let entries = fs::read_dir(base)
             .context("Failed to read CPU directory")?;
         
         let count = entries
@@ -79,14 +84,14 @@ impl CpuManager {
         Ok(count)
     }
 
-    fn detect_driver() -> CpuDriver {
-        if Path::new(INTEL_PSTATE_PATH).exists() {
+    fn detect_driver(base: &Path) -> CpuDriver {
+        if base.join("intel_pstate").exists() {
             CpuDriver::IntelPstate
-        } else if Path::new(AMD_PSTATE_PATH).exists() {
+        } else if base.join("amd_pstate").exists() {
             CpuDriver::AmdPstate
         } else {
             // Check for acpi-cpufreq by looking at scaling_driver
-            if let Ok(driver) = fs::read_to_string(format!("{}/cpu0/cpufreq/scaling_driver", CPUFREQ_BASE)) {
+            if let Ok(driver) = fs::read_to_string(base.join("cpu0/cpufreq/scaling_driver")) {
                 if driver.trim() == "acpi-cpufreq" {
                     return CpuDriver::AcpiCpufreq;
                 }
@@ -146,16 +151,43 @@ impl CpuManager {
     }
 
     pub fn get_core_status(&self, core: usize) -> Result<CoreStatus> {
+        let core_temps = self.hwmon.core_temperatures();
+        self.build_core_status(core, &core_temps)
+    }
+
+    pub fn get_all_core_status(&self) -> Result<Vec<CoreStatus>> {
+        let core_temps = self.hwmon.core_temperatures();
+        (0..self.core_count)
+            .map(|core| self.build_core_status(core, &core_temps))
+            .collect()
+    }
+
+    fn build_core_status(&self, core: usize, core_temps: &HashMap<(usize, usize), f32>) -> Result<CoreStatus> {
         if core >= self.core_count {
             anyhow::bail!("Core {} does not exist", core);
         }
 
+        // cpufreq files of an offline core cannot be read (EBUSY)
+        if !self.is_core_online(core)? {
+            return Ok(CoreStatus {
+                core_id: core,
+                current_freq: 0,
+                min_freq: 0,
+                max_freq: 0,
+                governor: String::new(),
+                online: false,
+                usage_percent: 0.0,
+                temperature_celsius: None,
+            });
+        }
+
         let current_freq = self.get_frequency(core)?;
         let min_freq = self.get_scaling_min_freq(core)?;
         let max_freq = self.get_scaling_max_freq(core)?;
         let governor = self.get_governor(core)?;
-        let online = self.is_core_online(core)?;
         let usage_percent = self.get_core_usage(core)?;
+        let temperature_celsius = self.get_core_topology(core)
+            .and_then(|topology| core_temps.get(&topology).copied());
 
         Ok(CoreStatus {
             core_id: core,
@@ -163,20 +195,28 @@ impl CpuManager {
             min_freq,
             max_freq,
             governor,
-            online,
+            online: true,
             usage_percent,
+            temperature_celsius,
         })
     }
 
-    pub fn get_all_core_status(&self) -> Result<Vec<CoreStatus>> {
-        (0..self.core_count)
-            .map(|core| self.get_core_status(core))
-            .collect()
+    /// (physical package id, core id) of a logical CPU; SMT siblings share a core id
+    pub fn get_core_topology(&self, core: usize) -> Option<(usize, usize)> {
+        let read = |attr: &str| -> Option<usize> {
+            fs::read_to_string(self.base_path.join(format!("cpu{}/topology/{}", core, attr)))
+                .ok()?
+                .trim()
+                .parse()
+                .ok()
+        };
+
+        Some((read("physical_package_id")?, read("core_id")?))
     }
 
     // Frequency control
     pub fn get_frequency(&self, core: usize) -> Result<u32> {
-        let path = format!("{}/cpu{}/cpufreq/scaling_cur_freq", CPUFREQ_BASE, core);
+        let path = self.base_path.join(format!("cpu{}/cpufreq/scaling_cur_freq", core));
         let freq_khz: u32 = fs::read_to_string(&path)
             .with_context(|| format!("Failed to read frequency for core {}", core))?
             .trim()
@@ -195,7 +235,7 @@ impl CpuManager {
         self.check_write_permission()?;
         
         let freq_khz = freq_mhz * 1000;
-        let path = format!("{}/cpu{}/cpufreq/scaling_setspeed", CPUFREQ_BASE, core);
+        let path = self.base_path.join(format!("cpu{}/cpufreq/scaling_setspeed", core));
         
         fs::write(&path, freq_khz.to_string())
             .with_context(|| format!("Failed to set frequency for core {}. Make sure you have root privileges.", core))?;
@@ -213,7 +253,7 @@ impl CpuManager {
 
     // Scaling limits
     pub fn get_scaling_min_freq(&self, core: usize) -> Result<u32> {
-        let path = format!("{}/cpu{}/cpufreq/scaling_min_freq", CPUFREQ_BASE, core);
+        let path = self.base_path.join(format!("cpu{}/cpufreq/scaling_min_freq", core));
         let freq_khz: u32 = fs::read_to_string(&path)
             .context("Failed to read min frequency")?
             .trim()
@@ -222,7 +262,7 @@ impl CpuManager {
     }
 
     pub fn get_scaling_max_freq(&self, core: usize) -> Result<u32> {
-        let path = format!("{}/cpu{}/cpufreq/scaling_max_freq", CPUFREQ_BASE, core);
+        let path = self.base_path.join(format!("cpu{}/cpufreq/scaling_max_freq", core));
         let freq_khz: u32 = fs::read_to_string(&path)
             .context("Failed to read max frequency")?
             .trim()
@@ -234,7 +274,7 @@ impl CpuManager {
         self.check_write_permission()?;
         
         let freq_khz = freq_mhz * 1000;
-        let path = format!("{}/cpu{}/cpufreq/scaling_min_freq", CPUFREQ_BASE, core);
+        let path = self.base_path.join(format!("cpu{}/cpufreq/scaling_min_freq", core));
         
         fs::write(&path, freq_khz.to_string())
             .with_context(|| format!("Failed to set min frequency for core {}. Run with sudo or enable PolicyKit.", core))?;
@@ -247,7 +287,7 @@ impl CpuManager {
         self.check_write_permission()?;
         
         let freq_khz = freq_mhz * 1000;
-        let path = format!("{}/cpu{}/cpufreq/scaling_max_freq", CPUFREQ_BASE, core);
+        let path = self.base_path.join(format!("cpu{}/cpufreq/scaling_max_freq", core));
         
         fs::write(&path, freq_khz.to_string())
             .with_context(|| format!("Failed to set max frequency for core {}. Run with sudo or enable PolicyKit.", core))?;
@@ -266,7 +306,7 @@ impl CpuManager {
 
     // Hardware limits
     pub fn get_hardware_min_freq(&self, core: usize) -> Result<u32> {
-        let path = format!("{}/cpu{}/cpufreq/cpuinfo_min_freq", CPUFREQ_BASE, core);
+        let path = self.base_path.join(format!("cpu{}/cpufreq/cpuinfo_min_freq", core));
         let freq_khz: u32 = fs::read_to_string(&path)
             .context("Failed to read hardware min frequency")?
             .trim()
@@ -275,7 +315,7 @@ impl CpuManager {
     }
 
     pub fn get_hardware_max_freq(&self, core: usize) -> Result<u32> {
-        let path = format!("{}/cpu{}/cpufreq/cpuinfo_max_freq", CPUFREQ_BASE, core);
+        let path = self.base_path.join(format!("cpu{}/cpufreq/cpuinfo_max_freq", core));
         let freq_khz: u32 = fs::read_to_string(&path)
             .context("Failed to read hardware max frequency")?
             .trim()
@@ -283,9 +323,25 @@ impl CpuManager {
         Ok(freq_khz / 1000)
     }
 
+    /// Highest frequency without turbo, from `base_frequency` (intel_pstate)
+    /// or `amd_pstate_nominal_freq` (amd-pstate)
+    pub fn get_base_freq(&self, core: usize) -> Result<u32> {
+        let cpufreq = self.base_path.join(format!("cpu{}/cpufreq", core));
+        let path = ["base_frequency", "amd_pstate_nominal_freq"]
+            .into_iter()
+            .map(|name| cpufreq.join(name))
+            .find(|path| path.exists())
+            .ok_or_else(|| anyhow::anyhow!("Base frequency not reported for core {}", core))?;
+        let freq_khz: u32 = fs::read_to_string(&path)
+            .context("Failed to read base frequency")?
+            .trim()
+            .parse()?;
+        Ok(freq_khz / 1000)
+    }
+
     // Governor control
     pub fn get_governor(&self, core: usize) -> Result<String> {
-        let path = format!("{}/cpu{}/cpufreq/scaling_governor", CPUFREQ_BASE, core);
+        let path = self.base_path.join(format!("cpu{}/cpufreq/scaling_governor", core));
         Ok(fs::read_to_string(&path)
             .context("Failed to read governor")?
             .trim()
@@ -307,7 +363,7 @@ impl CpuManager {
             anyhow::bail!("Governor '{}' is not available. Available: {:?}", governor, available);
         }
         
-        let path = format!("{}/cpu{}/cpufreq/scaling_governor", CPUFREQ_BASE, core);
+        let path = self.base_path.join(format!("cpu{}/cpufreq/scaling_governor", core));
         fs::write(&path, governor)
             .with_context(|| format!("Failed to set governor for core {}. Run with sudo or enable PolicyKit.", core))?;
         
@@ -323,7 +379,7 @@ impl CpuManager {
     }
 
     pub fn get_available_governors(&self, core: usize) -> Result<Vec<String>> {
-        let path = format!("{}/cpu{}/cpufreq/scaling_available_governors", CPUFREQ_BASE, core);
+        let path = self.base_path.join(format!("cpu{}/cpufreq/scaling_available_governors", core));
         let governors_str = fs::read_to_string(&path)
             .context("Failed to read available governors")?;
         
@@ -335,7 +391,7 @@ impl CpuManager {
 
     // Available frequencies
     pub fn get_available_frequencies(&self, core: usize) -> Result<Vec<u32>> {
-        let path = format!("{}/cpu{}/cpufreq/scaling_available_frequencies", CPUFREQ_BASE, core);
+        let path = self.base_path.join(format!("cpu{}/cpufreq/scaling_available_frequencies", core));
         
         if !Path::new(&path).exists() {
             return Ok(vec![]); // Not all drivers provide this
@@ -355,7 +411,7 @@ impl CpuManager {
     pub fn is_turbo_enabled(&self) -> Result<bool> {
         match self.driver {
             CpuDriver::IntelPstate => {
-                let path = format!("{}/no_turbo", INTEL_PSTATE_PATH);
+                let path = self.base_path.join("intel_pstate/no_turbo");
                 let no_turbo: u8 = fs::read_to_string(&path)
                     .context("Failed to read turbo state")?
                     .trim()
@@ -363,9 +419,9 @@ impl CpuManager {
                 Ok(no_turbo == 0)
             }
             CpuDriver::AcpiCpufreq => {
-                let path = "/sys/devices/system/cpu/cpufreq/boost";
-                if Path::new(path).exists() {
-                    let boost: u8 = fs::read_to_string(path)
+                let path = self.base_path.join("cpufreq/boost");
+                if path.exists() {
+                    let boost: u8 = fs::read_to_string(&path)
                         .context("Failed to read boost state")?
                         .trim()
                         .parse()?;
@@ -383,16 +439,16 @@ impl CpuManager {
         
         match self.driver {
             CpuDriver::IntelPstate => {
-                let path = format!("{}/no_turbo", INTEL_PSTATE_PATH);
+                let path = self.base_path.join("intel_pstate/no_turbo");
                 let value = if enable { "0" } else { "1" };
                 fs::write(&path, value)
                     .context("Failed to set turbo state. Run with sudo or enable PolicyKit.")?;
             }
             CpuDriver::AcpiCpufreq => {
-                let path = "/sys/devices/system/cpu/cpufreq/boost";
-                if Path::new(path).exists() {
+                let path = self.base_path.join("cpufreq/boost");
+                if path.exists() {
                     let value = if enable { "1" } else { "0" };
-                    fs::write(path, value)
+                    fs::write(&path, value)
                         .context("Failed to set boost state")?;
                 } else {
                     anyhow::bail!("Turbo boost control not available");
@@ -414,7 +470,7 @@ impl CpuManager {
         self.check_write_permission()?;
 
         for core in 0..self.core_count {
-            let path = format!("{}/cpu{}/cpufreq/energy_performance_preference", CPUFREQ_BASE, core);
+            let path = self.base_path.join(format!("cpu{}/cpufreq/energy_performance_preference", core));
             if Path::new(&path).exists() {
                 fs::write(&path, epp)
                     .with_context(|| format!("Failed to set EPP for core {}", core))?;
@@ -426,7 +482,7 @@ impl CpuManager {
     }
 
     pub fn get_epp(&self, core: usize) -> Result<String> {
-        let path = format!("{}/cpu{}/cpufreq/energy_performance_preference", CPUFREQ_BASE, core);
+        let path = self.base_path.join(format!("cpu{}/cpufreq/energy_performance_preference", core));
         if Path::new(&path).exists() {
             Ok(fs::read_to_string(&path)?.trim().to_string())
         } else {
@@ -440,7 +496,7 @@ impl CpuManager {
             return Ok(true); // Core 0 is always online
         }
         
-        let path = format!("{}/cpu{}/online", CPUFREQ_BASE, core);
+        let path = self.base_path.join(format!("cpu{}/online", core));
         if !Path::new(&path).exists() {
             return Ok(true); // If the file doesn't exist, assume online
         }
@@ -459,7 +515,7 @@ impl CpuManager {
         
         self.check_write_permission()?;
         
-        let path = format!("{}/cpu{}/online", CPUFREQ_BASE, core);
+        let path = self.base_path.join(format!("cpu{}/online", core));
         let value = if online { "1" } else { "0" };
         
         fs::write(&path, value)
@@ -469,6 +525,26 @@ impl CpuManager {
         Ok(())
     }
 
+    // PM QoS resume latency
+    /// Returns the per-CPU resume latency limit in microseconds.
+    /// `None` means "n/a": no resume latency is acceptable (idle states disabled).
+    pub fn get_pm_qos_resume_latency(&self, core: usize) -> Result<Option<u32>> {
+        let path = self.base_path.join(format!("cpu{}/power/pm_qos_resume_latency_us", core));
+        let value = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read PM QoS resume latency for core {}", core))?;
+
+        match value.trim() {
+            "n/a" => Ok(None),
+            latency => Ok(Some(latency.parse().context("Failed to parse PM QoS resume latency")?)),
+        }
+    }
+
+    pub fn get_all_pm_qos_resume_latencies(&self) -> Result<Vec<Option<u32>>> {
+        (0..self.core_count)
+            .map(|core| self.get_pm_qos_resume_latency(core))
+            .collect()
+    }
+
     // Core usage
     fn get_core_usage(&self, _core: usize) -> Result<f32> {
         // This is a simplified version - real implementation would use procfs
@@ -494,6 +570,13 @@ impl CpuManager {
         self.core_count
     }
 
+    /// Cores whose cpufreq files can be used; offline ones fail with EBUSY
+    pub fn online_cores(&self) -> Vec<usize> {
+        (0..self.core_count)
+            .filter(|&core| self.is_core_online(core).unwrap_or(true))
+            .collect()
+    }
+
     pub fn driver(&self) -> CpuDriver {
         self.driver
     }
@@ -502,6 +585,7 @@ impl CpuManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::testing::{write_cpus, TempDir};
 
     #[test]
     fn test_cpu_manager_creation() {
@@ -514,4 +598,17 @@ mod tests {
         let manager = CpuManager::new().unwrap();
         assert!(manager.core_count() > 0);
     }
+
+    #[test]
+    fn test_offline_core_status() {
+        let base = TempDir::new("cpu");
+        write_cpus(&base, &[true, false, true]);
+
+        let manager = CpuManager::from_base(&base).unwrap();
+        let cores = manager.get_all_core_status().unwrap();
+        assert_eq!(cores.len(), 3);
+        assert!(!cores[1].online);
+        assert_eq!((cores[2].current_freq, cores[2].max_freq), (2400, 4000));
+        assert_eq!(cores[2].governor, "powersave");
+    }
 }
@@ -0,0 +1,191 @@
+// Package power sampling module
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use crate::backend::rapl::{RaplManager, RaplZoneKind};
+
+const HWMON_BASE: &str = "/sys/class/hwmon";
+
+/// Power drawn by a single energy counter over the last sampling interval
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZonePower {
+    pub name: String,
+    pub kind: RaplZoneKind,
+    pub watts: f32,
+}
+
+/// A monotonically increasing energy counter in microjoules
+struct EnergyCounter {
+    name: String,
+    kind: RaplZoneKind,
+    path: PathBuf,
+    max_range_uj: u64,
+}
+
+/// Computes per-zone power from powercap (Intel/AMD RAPL) or `amd_energy`
+/// hwmon counters by differencing consecutive samples.
+pub struct EnergySampler {
+    counters: Vec<EnergyCounter>,
+    last: Option<(Instant, Vec<u64>)>,
+}
+
+impl EnergySampler {
+    pub fn new() -> Result<Self> {
+        let mut counters = Self::discover_rapl_counters();
+        if counters.is_empty() {
+            counters = Self::discover_amd_energy_counters(Path::new(HWMON_BASE));
+        }
+
+        if counters.is_empty() {
+            anyhow::bail!("No energy counters found (powercap or amd_energy)");
+        }
+
+        log::info!("Discovered {} energy counters", counters.len());
+        Ok(Self { counters, last: None })
+    }
+
+    fn discover_rapl_counters() -> Vec<EnergyCounter> {
+        let Ok(rapl) = RaplManager::new() else {
+            return vec![];
+        };
+
+        (0..rapl.get_zone_count())
+            .filter_map(|zone| {
+                let info = rapl.get_zone_info(zone).ok()?;
+                Some(EnergyCounter {
+                    path: rapl.energy_counter_path(zone).ok()?,
+                    name: info.name,
+                    kind: info.kind,
+                    max_range_uj: info.max_energy_range_uj,
+                })
+            })
+            .collect()
+    }
+
+    fn discover_amd_energy_counters(base: &Path) -> Vec<EnergyCounter> {
+        let mut counters = vec![];
+        let Ok(entries) = fs::read_dir(base) else {
+            return counters;
+        };
+
+        for hwmon in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            let is_amd_energy = fs::read_to_string(hwmon.join("name"))
+                .map(|name| name.trim() == "amd_energy")
+                .unwrap_or(false);
+            if !is_amd_energy {
+                continue;
+            }
+
+            let mut index = 1;
+            while hwmon.join(format!("energy{}_input", index)).exists() {
+                let label = fs::read_to_string(hwmon.join(format!("energy{}_label", index)))
+                    .map(|l| l.trim().to_string())
+                    .unwrap_or_else(|_| format!("energy{}", index));
+                let kind = if label.starts_with("Esocket") {
+                    RaplZoneKind::Package
+                } else if label.starts_with("Ecore") {
+                    RaplZoneKind::Core
+                } else {
+                    RaplZoneKind::Unknown
+                };
+
+                counters.push(EnergyCounter {
+                    name: label,
+                    kind,
+                    path: hwmon.join(format!("energy{}_input", index)),
+                    // amd_energy accumulates into a 64-bit counter
+                    max_range_uj: u64::MAX,
+                });
+                index += 1;
+            }
+        }
+
+        counters
+    }
+
+    /// Take a new sample. Returns `None` on the first call, since power can
+    /// only be computed from the difference between two samples.
+    pub fn sample(&mut self) -> Result<Option<Vec<ZonePower>>> {
+        let now = Instant::now();
+        let readings = self.counters
+            .iter()
+            .map(|c| {
+                fs::read_to_string(&c.path)
+                    .with_context(|| format!("Failed to read energy counter {}", c.name))?
+                    .trim()
+                    .parse::<u64>()
+                    .context("Failed to parse energy counter")
+            })
+            .collect::<Result<Vec<u64>>>()?;
+
+        let previous = self.last.replace((now, readings.clone()));
+        let Some((last_time, last_readings)) = previous else {
+            return Ok(None);
+        };
+
+        let elapsed = now.duration_since(last_time).as_secs_f64();
+        if elapsed <= 0.0 {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            self.counters
+                .iter()
+                .zip(last_readings.iter().zip(&readings))
+                .map(|(counter, (&prev, &cur))| ZonePower {
+                    name: counter.name.clone(),
+                    kind: counter.kind,
+                    watts: (energy_delta_uj(prev, cur, counter.max_range_uj) as f64 / elapsed / 1_000_000.0) as f32,
+                })
+                .collect(),
+        ))
+    }
+
+    /// Total power of all package-level zones
+    pub fn package_watts(zones: &[ZonePower]) -> Option<f32> {
+        let package: Vec<f32> = zones
+            .iter()
+            .filter(|z| z.kind == RaplZoneKind::Package)
+            .map(|z| z.watts)
+            .collect();
+
+        (!package.is_empty()).then(|| package.iter().sum())
+    }
+}
+
+/// Energy consumed between two counter readings, accounting for the counter
+/// wrapping around. It counts over `0..=max_range_uj`, so the wrap itself is one step.
+fn energy_delta_uj(previous: u64, current: u64, max_range_uj: u64) -> u64 {
+    if current >= previous {
+        current - previous
+    } else {
+        max_range_uj.saturating_sub(previous).saturating_add(current).saturating_add(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_energy_delta_wraparound() {
+        assert_eq!(energy_delta_uj(1_000, 6_000, 262_143_328_850), 5_000);
+        assert_eq!(energy_delta_uj(262_143_328_000, 150, 262_143_328_850), 1_001);
+        assert_eq!(energy_delta_uj(262_143_328_850, 0, 262_143_328_850), 1);
+        assert_eq!(energy_delta_uj(u64::MAX - 9, 10, u64::MAX), 20);
+    }
+
+    #[test]
+    fn test_package_watts() {
+        let zones = vec![
+            ZonePower { name: "package-0".to_string(), kind: RaplZoneKind::Package, watts: 10.0 },
+            ZonePower { name: "core".to_string(), kind: RaplZoneKind::Core, watts: 6.0 },
+            ZonePower { name: "package-1".to_string(), kind: RaplZoneKind::Package, watts: 5.5 },
+        ];
+        assert_eq!(EnergySampler::package_watts(&zones), Some(15.5));
+        assert_eq!(EnergySampler::package_watts(&zones[1..2]), None);
+    }
+}
@@ -0,0 +1,392 @@
+// hwmon fan control module
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const HWMON_BASE: &str = "/sys/class/hwmon";
+
+/// `pwmN_enable` value for manual PWM control
+const PWM_ENABLE_MANUAL: &str = "1";
+
+/// `pwmN_enable` files currently switched to manual and the value each held
+/// before, restored by the panic hook
+static MANUAL_FANS: Mutex<Vec<(PathBuf, String)>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FanCurvePoint {
+    pub temp_celsius: f32,
+    pub pwm: u8,
+}
+
+/// Temperature to PWM mapping, linearly interpolated between points
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FanCurve(pub Vec<FanCurvePoint>);
+
+impl FanCurve {
+    /// Keeps fans slow until the CPU gets genuinely warm
+    pub fn quiet() -> Self {
+        Self(vec![
+            FanCurvePoint { temp_celsius: 55.0, pwm: 60 },
+            FanCurvePoint { temp_celsius: 70.0, pwm: 100 },
+            FanCurvePoint { temp_celsius: 85.0, pwm: 255 },
+        ])
+    }
+
+    pub fn pwm_for(&self, temp: f32) -> u8 {
+        let mut points = self.0.clone();
+        points.sort_by(|a, b| a.temp_celsius.total_cmp(&b.temp_celsius));
+
+        let (Some(first), Some(last)) = (points.first(), points.last()) else {
+            // No curve means no opinion; run the fans at full speed to be safe
+            return u8::MAX;
+        };
+        if temp <= first.temp_celsius {
+            return first.pwm;
+        }
+        if temp >= last.temp_celsius {
+            return last.pwm;
+        }
+
+        points
+            .windows(2)
+            .find(|w| temp <= w[1].temp_celsius)
+            .map(|w| {
+                let span = w[1].temp_celsius - w[0].temp_celsius;
+                let ratio = if span > 0.0 { (temp - w[0].temp_celsius) / span } else { 1.0 };
+                (w[0].pwm as f32 + ratio * (w[1].pwm as f32 - w[0].pwm as f32)).round() as u8
+            })
+            .unwrap_or(last.pwm)
+    }
+}
+
+impl Default for FanCurve {
+    fn default() -> Self {
+        Self(vec![
+            FanCurvePoint { temp_celsius: 40.0, pwm: 70 },
+            FanCurvePoint { temp_celsius: 60.0, pwm: 120 },
+            FanCurvePoint { temp_celsius: 75.0, pwm: 180 },
+            FanCurvePoint { temp_celsius: 85.0, pwm: 255 },
+        ])
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Fan {
+    pub chip: String,
+    pub index: u32,
+    chip_path: PathBuf,
+    pwm: PathBuf,
+    enable: PathBuf,
+    input: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FanInfo {
+    pub chip: String,
+    pub index: u32,
+    pub rpm: Option<u32>,
+    pub pwm: Option<u8>,
+}
+
+pub struct FanManager {
+    fans: Vec<Fan>,
+}
+
+impl FanManager {
+    pub fn new() -> Result<Self> {
+        let manager = Self::from_base(Path::new(HWMON_BASE));
+        log::info!("Discovered {} controllable fans", manager.fans.len());
+        Ok(manager)
+    }
+
+    fn from_base(base: &Path) -> Self {
+        let mut chips: Vec<PathBuf> = fs::read_dir(base)
+            .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+            .unwrap_or_default();
+        chips.sort();
+
+        let mut fans = vec![];
+        for chip_path in chips {
+            let chip = fs::read_to_string(chip_path.join("name"))
+                .map(|n| n.trim().to_string())
+                .unwrap_or_default();
+
+            let mut indices: Vec<u32> = fs::read_dir(&chip_path)
+                .map(|entries| {
+                    entries
+                        .filter_map(|e| e.ok())
+                        .filter_map(|e| e.file_name().to_str()?.strip_prefix("pwm")?.parse().ok())
+                        .collect()
+                })
+                .unwrap_or_default();
+            indices.sort();
+
+            for index in indices {
+                let enable = chip_path.join(format!("pwm{}_enable", index));
+                if !enable.exists() {
+                    continue;
+                }
+                let input = chip_path.join(format!("fan{}_input", index));
+
+                fans.push(Fan {
+                    chip: chip.clone(),
+                    index,
+                    chip_path: chip_path.clone(),
+                    pwm: chip_path.join(format!("pwm{}", index)),
+                    enable,
+                    input: input.exists().then_some(input),
+                });
+            }
+        }
+
+        Self { fans }
+    }
+
+    pub fn fans(&self) -> &[Fan] {
+        &self.fans
+    }
+
+    /// Keep only the fans fan control may drive: the `configured` ones, or if
+    /// none are configured, those on the CPU temperature sensor's chip. GPU,
+    /// case and pump fans are left alone unless listed explicitly.
+    pub fn select(&mut self, configured: &[String], cpu_chip: Option<&Path>) {
+        for id in configured {
+            if !self.fans.iter().any(|fan| &fan.id() == id) {
+                log::warn!("Configured fan {} not found", id);
+            }
+        }
+
+        self.fans.retain(|fan| {
+            if configured.is_empty() {
+                cpu_chip == Some(fan.chip_path.as_path())
+            } else {
+                configured.contains(&fan.id())
+            }
+        });
+    }
+
+    pub fn read_rpm(&self, fan: &Fan) -> Result<Option<u32>> {
+        let Some(ref input) = fan.input else {
+            return Ok(None);
+        };
+        let rpm = fs::read_to_string(input)
+            .with_context(|| format!("Failed to read {} fan{} speed", fan.chip, fan.index))?
+            .trim()
+            .parse()
+            .context("Failed to parse fan speed")?;
+        Ok(Some(rpm))
+    }
+
+    pub fn read_pwm(&self, fan: &Fan) -> Result<u8> {
+        fs::read_to_string(&fan.pwm)
+            .with_context(|| format!("Failed to read {} pwm{}", fan.chip, fan.index))?
+            .trim()
+            .parse()
+            .context("Failed to parse PWM value")
+    }
+
+    pub fn get_all_fans(&self) -> Vec<FanInfo> {
+        self.fans
+            .iter()
+            .map(|fan| FanInfo {
+                chip: fan.chip.clone(),
+                index: fan.index,
+                rpm: self.read_rpm(fan).ok().flatten(),
+                pwm: self.read_pwm(fan).ok(),
+            })
+            .collect()
+    }
+
+    /// Take manual control of a fan. Its previous mode is put back by
+    /// `restore`, or by the panic hook if the process crashes.
+    pub fn set_manual(&self, fan: &Fan) -> Result<()> {
+        let mut manual = MANUAL_FANS.lock().unwrap_or_else(|e| e.into_inner());
+        if !manual.iter().any(|(enable, _)| enable == &fan.enable) {
+            let original = fs::read_to_string(&fan.enable)
+                .with_context(|| format!("Failed to read {} pwm{} mode", fan.chip, fan.index))?;
+            manual.push((fan.enable.clone(), original.trim().to_string()));
+        }
+
+        fs::write(&fan.enable, PWM_ENABLE_MANUAL)
+            .with_context(|| format!("Failed to take control of {} pwm{}. Run with sudo or enable PolicyKit.", fan.chip, fan.index))
+    }
+
+    pub fn set_pwm(&self, fan: &Fan, pwm: u8) -> Result<()> {
+        fs::write(&fan.pwm, pwm.to_string())
+            .with_context(|| format!("Failed to set {} pwm{} to {}", fan.chip, fan.index, pwm))
+    }
+
+    /// Put a fan back in the mode it was in before `set_manual`
+    pub fn restore(&self, fan: &Fan) -> Result<()> {
+        let mut manual = MANUAL_FANS.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(position) = manual.iter().position(|(enable, _)| enable == &fan.enable) else {
+            return Ok(());
+        };
+
+        let (enable, original) = manual.remove(position);
+        fs::write(&enable, &original)
+            .with_context(|| format!("Failed to restore {} pwm{} to mode {}", fan.chip, fan.index, original))
+    }
+}
+
+impl Fan {
+    /// `<chip>/pwm<N>`, as listed in the `fans` setting
+    pub fn id(&self) -> String {
+        format!("{}/pwm{}", self.chip, self.index)
+    }
+}
+
+/// Put every fan this process took over back in its original mode
+pub fn restore_fan_control() {
+    let mut manual = MANUAL_FANS.lock().unwrap_or_else(|e| e.into_inner());
+    for (enable, original) in manual.drain(..) {
+        if let Err(e) = fs::write(&enable, &original) {
+            log::error!("Failed to restore fan mode {} for {}: {}", original, enable.display(), e);
+        }
+    }
+}
+
+/// Restore fan modes before a panic takes the process down
+pub fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore_fan_control();
+        previous(info);
+    }));
+}
+
+/// Drives the selected fans from a temperature curve. Rising temperatures
+/// are followed immediately; falling ones only lower the fan speed once they
+/// drop more than the hysteresis below the temperature the speed was set for.
+pub struct FanController {
+    manager: FanManager,
+    curve: FanCurve,
+    hysteresis: f32,
+    reference_temp: Option<f32>,
+    current_pwm: Option<u8>,
+}
+
+impl FanController {
+    pub fn new(manager: FanManager, curve: FanCurve, hysteresis: f32) -> Self {
+        Self {
+            manager,
+            curve,
+            hysteresis,
+            reference_temp: None,
+            current_pwm: None,
+        }
+    }
+
+    pub fn set_curve(&mut self, curve: FanCurve) {
+        self.curve = curve;
+        self.reference_temp = None;
+        self.current_pwm = None;
+    }
+
+    pub fn update(&mut self, temp: f32) -> Result<()> {
+        let reference = self.next_reference(temp);
+        let pwm = self.curve.pwm_for(reference);
+        if self.current_pwm == Some(pwm) {
+            return Ok(());
+        }
+
+        log::debug!("Fan control: {:.1}°C, setting PWM to {}", temp, pwm);
+        for fan in self.manager.fans() {
+            if self.current_pwm.is_none() {
+                self.manager.set_manual(fan)?;
+            }
+            self.manager.set_pwm(fan, pwm)?;
+        }
+        self.current_pwm = Some(pwm);
+        Ok(())
+    }
+
+    fn next_reference(&mut self, temp: f32) -> f32 {
+        let reference = match self.reference_temp {
+            Some(last) => temp.max(last.min(temp + self.hysteresis)),
+            None => temp,
+        };
+        self.reference_temp = Some(reference);
+        reference
+    }
+
+    pub fn restore(&mut self) {
+        for fan in self.manager.fans() {
+            if let Err(e) = self.manager.restore(fan) {
+                log::error!("{}", e);
+            }
+        }
+        self.reference_temp = None;
+        self.current_pwm = None;
+    }
+}
+
+impl Drop for FanController {
+    fn drop(&mut self) {
+        self.restore();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::TempDir;
+
+    #[test]
+    fn test_fan_curve_interpolation() {
+        let curve = FanCurve::default();
+        assert_eq!(curve.pwm_for(20.0), 70);
+        assert_eq!(curve.pwm_for(50.0), 95);
+        assert_eq!(curve.pwm_for(90.0), 255);
+        assert_eq!(FanCurve(vec![]).pwm_for(50.0), 255);
+    }
+
+    #[test]
+    fn test_fan_discovery_and_hysteresis() {
+        let base = TempDir::new("fan");
+        let chip = base.join("hwmon2");
+        fs::create_dir_all(&chip).unwrap();
+        fs::write(chip.join("name"), "nct6775\n").unwrap();
+        fs::write(chip.join("pwm1"), "128\n").unwrap();
+        // SmartFan IV, which must come back rather than plain automatic mode
+        fs::write(chip.join("pwm1_enable"), "5\n").unwrap();
+        fs::write(chip.join("fan1_input"), "1450\n").unwrap();
+        // pwm2 has no enable file and cannot be controlled
+        fs::write(chip.join("pwm2"), "255\n").unwrap();
+        let gpu = base.join("hwmon4");
+        fs::create_dir_all(&gpu).unwrap();
+        fs::write(gpu.join("name"), "amdgpu\n").unwrap();
+        fs::write(gpu.join("pwm1"), "80\n").unwrap();
+        fs::write(gpu.join("pwm1_enable"), "2\n").unwrap();
+
+        let mut manager = FanManager::from_base(&base);
+        assert_eq!(manager.fans().len(), 2);
+        manager.select(&[], None);
+        assert!(manager.fans().is_empty());
+
+        let mut manager = FanManager::from_base(&base);
+        manager.select(&["amdgpu/pwm1".to_string()], None);
+        assert_eq!(manager.fans()[0].id(), "amdgpu/pwm1");
+
+        let mut manager = FanManager::from_base(&base);
+        manager.select(&[], Some(&chip));
+        assert_eq!(manager.fans().len(), 1);
+        assert_eq!(manager.read_rpm(&manager.fans()[0]).unwrap(), Some(1450));
+
+        let mut controller = FanController::new(manager, FanCurve::default(), 3.0);
+        controller.update(75.0).unwrap();
+        assert_eq!(fs::read_to_string(chip.join("pwm1_enable")).unwrap(), "1");
+        assert_eq!(fs::read_to_string(chip.join("pwm1")).unwrap(), "180");
+
+        // A small dip is absorbed by the hysteresis, a larger one is not
+        assert_eq!(controller.next_reference(73.0), 75.0);
+        assert_eq!(controller.next_reference(70.0), 73.0);
+
+        drop(controller);
+        assert_eq!(fs::read_to_string(chip.join("pwm1_enable")).unwrap(), "5");
+        assert_eq!(fs::read_to_string(gpu.join("pwm1_enable")).unwrap(), "2\n");
+    }
+}
@@ -0,0 +1,273 @@
+// hwmon temperature sensor module
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const HWMON_BASE: &str = "/sys/class/hwmon";
+
+/// hwmon drivers that report CPU temperatures, in order of preference
+const CPU_HWMON_CHIPS: &[&str] = &["coretemp", "zenpower", "k10temp", "cpu_thermal"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SensorKind {
+    /// Whole-package temperature (coretemp "Package id N", or an unlabeled SoC sensor)
+    Package,
+    /// Per-core temperature keyed by the topology core_id
+    Core(usize),
+    /// AMD control temperature (may carry a fan-curve offset)
+    Tctl,
+    /// AMD actual die temperature
+    Tdie,
+    /// AMD per-CCD temperature
+    Tccd(usize),
+    Other,
+}
+
+#[derive(Debug, Clone)]
+pub struct HwmonSensor {
+    pub chip: String,
+    pub label: String,
+    pub kind: SensorKind,
+    chip_path: PathBuf,
+    input: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemperatureReading {
+    pub chip: String,
+    pub label: String,
+    pub kind: SensorKind,
+    pub temp_celsius: f32,
+}
+
+pub struct HwmonManager {
+    sensors: Vec<HwmonSensor>,
+}
+
+impl HwmonSensor {
+    /// The `/sys/class/hwmon/hwmonN` directory of the chip
+    pub fn chip_path(&self) -> &Path {
+        &self.chip_path
+    }
+}
+
+impl HwmonManager {
+    pub fn new() -> Result<Self> {
+        let manager = Self::from_base(Path::new(HWMON_BASE));
+        log::info!("Discovered {} hwmon CPU temperature sensors", manager.sensors.len());
+        Ok(manager)
+    }
+
+    fn from_base(base: &Path) -> Self {
+        let mut chips: Vec<PathBuf> = fs::read_dir(base)
+            .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+            .unwrap_or_default();
+        chips.sort();
+
+        let mut sensors = vec![];
+        for chip_path in chips {
+            let Ok(chip) = fs::read_to_string(chip_path.join("name")) else {
+                continue;
+            };
+            let chip = chip.trim().to_string();
+            if !CPU_HWMON_CHIPS.contains(&chip.as_str()) {
+                continue;
+            }
+
+            // coretemp numbers inputs by core id, so the indices can have gaps
+            let mut indices: Vec<usize> = fs::read_dir(&chip_path)
+                .map(|entries| {
+                    entries
+                        .filter_map(|e| e.ok())
+                        .filter_map(|e| e.file_name().to_str()?.strip_prefix("temp")?.strip_suffix("_input")?.parse().ok())
+                        .collect()
+                })
+                .unwrap_or_default();
+            indices.sort_unstable();
+
+            for index in indices {
+                let label = fs::read_to_string(chip_path.join(format!("temp{}_label", index)))
+                    .map(|l| l.trim().to_string())
+                    .unwrap_or_else(|_| format!("temp{}", index));
+
+                sensors.push(HwmonSensor {
+                    kind: SensorKind::from_label(&chip, &label),
+                    chip: chip.clone(),
+                    label,
+                    chip_path: chip_path.clone(),
+                    input: chip_path.join(format!("temp{}_input", index)),
+                });
+            }
+        }
+
+        Self { sensors }
+    }
+
+    pub fn sensors(&self) -> &[HwmonSensor] {
+        &self.sensors
+    }
+
+    pub fn find(&self, chip: &str, label: &str) -> Option<&HwmonSensor> {
+        self.sensors.iter().find(|s| s.chip == chip && s.label == label)
+    }
+
+    pub fn read(&self, sensor: &HwmonSensor) -> Result<f32> {
+        let temp_millicelsius: i32 = fs::read_to_string(&sensor.input)
+            .with_context(|| format!("Failed to read {} {} temperature", sensor.chip, sensor.label))?
+            .trim()
+            .parse()
+            .context("Failed to parse temperature")?;
+
+        Ok(temp_millicelsius as f32 / 1000.0)
+    }
+
+    pub fn read_all(&self) -> Vec<TemperatureReading> {
+        self.sensors
+            .iter()
+            .filter_map(|sensor| {
+                Some(TemperatureReading {
+                    chip: sensor.chip.clone(),
+                    label: sensor.label.clone(),
+                    kind: sensor.kind,
+                    temp_celsius: self.read(sensor).ok()?,
+                })
+            })
+            .collect()
+    }
+
+    /// Per-core temperatures keyed by (physical package id, core id).
+    /// coretemp registers one chip per package, identified by its "Package id N" sensor.
+    pub fn core_temperatures(&self) -> HashMap<(usize, usize), f32> {
+        let package_of = |chip_path: &Path| {
+            self.sensors
+                .iter()
+                .filter(|s| s.chip_path == chip_path && s.kind == SensorKind::Package)
+                .find_map(|s| s.label.strip_prefix("Package id ")?.trim().parse().ok())
+                .unwrap_or(0)
+        };
+
+        self.sensors
+            .iter()
+            .filter_map(|sensor| {
+                let SensorKind::Core(core_id) = sensor.kind else {
+                    return None;
+                };
+                let temp = self.read(sensor).ok()?;
+                Some(((package_of(&sensor.chip_path), core_id), temp))
+            })
+            .collect()
+    }
+
+    /// The sensor that best represents overall CPU temperature. The choice
+    /// depends only on which sensors exist, never on their current values.
+    pub fn default_cpu_sensor(&self) -> Option<&HwmonSensor> {
+        self.sensors.iter().min_by_key(|s| {
+            let chip_rank = CPU_HWMON_CHIPS.iter().position(|c| *c == s.chip).unwrap_or(usize::MAX);
+            (s.kind.rank(), chip_rank)
+        })
+    }
+}
+
+impl SensorKind {
+    fn from_label(chip: &str, label: &str) -> Self {
+        if let Some(core) = label.strip_prefix("Core ") {
+            return core.trim().parse().map(SensorKind::Core).unwrap_or(SensorKind::Other);
+        }
+        if label.starts_with("Package id") {
+            return SensorKind::Package;
+        }
+        if let Some(ccd) = label.strip_prefix("Tccd") {
+            return ccd.parse().map(SensorKind::Tccd).unwrap_or(SensorKind::Other);
+        }
+
+        match label {
+            "Tctl" => SensorKind::Tctl,
+            "Tdie" => SensorKind::Tdie,
+            // SoC drivers expose a single unlabeled sensor
+            _ if chip == "cpu_thermal" => SensorKind::Package,
+            _ => SensorKind::Other,
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            SensorKind::Package => 0,
+            SensorKind::Tdie => 1,
+            SensorKind::Tctl => 2,
+            SensorKind::Tccd(_) => 3,
+            SensorKind::Core(_) => 4,
+            SensorKind::Other => 5,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::TempDir;
+
+    fn write_chip(base: &Path, hwmon: &str, name: &str, sensors: &[(&str, i32)]) {
+        let dir = base.join(hwmon);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("name"), format!("{}\n", name)).unwrap();
+        for (i, (label, temp)) in sensors.iter().enumerate() {
+            fs::write(dir.join(format!("temp{}_label", i + 1)), format!("{}\n", label)).unwrap();
+            fs::write(dir.join(format!("temp{}_input", i + 1)), format!("{}\n", temp)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_cpu_sensor_discovery() {
+        let base = TempDir::new("hwmon");
+        write_chip(&base, "hwmon0", "iwlwifi_1", &[("temp1", 99000)]);
+        write_chip(&base, "hwmon1", "k10temp", &[("Tctl", 71000), ("Tdie", 61000), ("Tccd1", 58500)]);
+        write_chip(&base, "hwmon2", "coretemp", &[("Core 0", 52000), ("Package id 0", 55000), ("Core 4", 54000)]);
+
+        let manager = HwmonManager::from_base(&base);
+        assert_eq!(manager.sensors().len(), 6);
+
+        let default = manager.default_cpu_sensor().unwrap();
+        assert_eq!((default.chip.as_str(), default.label.as_str()), ("coretemp", "Package id 0"));
+        assert_eq!(manager.read(default).unwrap(), 55.0);
+
+        let core4 = manager.find("coretemp", "Core 4").unwrap();
+        assert_eq!(core4.kind, SensorKind::Core(4));
+        assert_eq!(manager.find("k10temp", "Tccd1").unwrap().kind, SensorKind::Tccd(1));
+
+        let base = TempDir::new("hwmon-tdie");
+        write_chip(&base, "hwmon3", "k10temp", &[("Tctl", 71000), ("Tdie", 61000)]);
+        let manager = HwmonManager::from_base(&base);
+        assert_eq!(manager.default_cpu_sensor().unwrap().kind, SensorKind::Tdie);
+    }
+
+    #[test]
+    fn test_core_temperatures_per_package() {
+        let base = TempDir::new("hwmon-cores");
+        write_chip(&base, "hwmon1", "coretemp", &[("Package id 0", 60000), ("Core 0", 58000), ("Core 1", 59000)]);
+        write_chip(&base, "hwmon2", "coretemp", &[("Package id 1", 70000), ("Core 0", 68000)]);
+
+        let temps = HwmonManager::from_base(&base).core_temperatures();
+        assert_eq!(temps.len(), 3);
+        assert_eq!(temps.get(&(0, 1)), Some(&59.0));
+        assert_eq!(temps.get(&(1, 0)), Some(&68.0));
+    }
+
+    #[test]
+    fn test_sensor_indices_with_gaps() {
+        let base = TempDir::new("hwmon-gaps");
+        let dir = base.join("hwmon0");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("name"), "coretemp\n").unwrap();
+        for (index, label, temp) in [(1, "Package id 0", 60000), (2, "Core 0", 55000), (6, "Core 4", 57000), (10, "Core 8", 59000)] {
+            fs::write(dir.join(format!("temp{}_label", index)), format!("{}\n", label)).unwrap();
+            fs::write(dir.join(format!("temp{}_input", index)), format!("{}\n", temp)).unwrap();
+        }
+
+        let manager = HwmonManager::from_base(&base);
+        let labels: Vec<&str> = manager.sensors().iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, vec!["Package id 0", "Core 0", "Core 4", "Core 8"]);
+        assert_eq!(manager.core_temperatures().get(&(0, 8)), Some(&59.0));
+    }
+}
@@ -0,0 +1,88 @@
+// CPU load sampling module
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+
+const PROC_STAT: &str = "/proc/stat";
+
+/// Cumulative jiffies for one CPU from `/proc/stat`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CpuTimes {
+    busy: u64,
+    total: u64,
+}
+
+/// Per-core utilization between successive samples of `/proc/stat`
+pub struct CpuLoadSampler {
+    last: Option<HashMap<usize, CpuTimes>>,
+}
+
+impl CpuLoadSampler {
+    pub fn new() -> Self {
+        Self { last: None }
+    }
+
+    /// Usage in percent per core since the previous call. The first call
+    /// only records a baseline and returns an empty map.
+    pub fn sample(&mut self) -> Result<HashMap<usize, f32>> {
+        let stat = fs::read_to_string(PROC_STAT).context("Failed to read /proc/stat")?;
+        let current = parse_proc_stat(&stat);
+
+        let usage = match &self.last {
+            Some(last) => current
+                .iter()
+                .filter_map(|(core, now)| {
+                    let prev = last.get(core)?;
+                    let total = now.total.saturating_sub(prev.total);
+                    let busy = now.busy.saturating_sub(prev.busy);
+                    (total > 0).then(|| (*core, busy as f32 / total as f32 * 100.0))
+                })
+                .collect(),
+            None => HashMap::new(),
+        };
+
+        self.last = Some(current);
+        Ok(usage)
+    }
+}
+
+impl Default for CpuLoadSampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Parse the per-CPU `cpuN` lines. Offline CPUs are absent, so cores are
+/// keyed by their id rather than their position.
+fn parse_proc_stat(stat: &str) -> HashMap<usize, CpuTimes> {
+    stat.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let core = fields.next()?.strip_prefix("cpu")?.parse().ok()?;
+            let values: Vec<u64> = fields.filter_map(|v| v.parse().ok()).collect();
+
+            // user nice system idle iowait irq softirq steal; guest time is already in user
+            let total: u64 = values.iter().take(8).sum();
+            let idle = values.get(3).copied().unwrap_or(0) + values.get(4).copied().unwrap_or(0);
+            Some((core, CpuTimes { busy: total.saturating_sub(idle), total }))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_stat() {
+        let stat = "cpu  400 0 100 1500 0 0 0 0 0 0\n\
+                    cpu0 300 0 50 600 50 0 0 0 0 0\n\
+                    cpu2 100 0 50 850 0 0 0 0 0 0\n\
+                    intr 12345\n";
+
+        let times = parse_proc_stat(stat);
+        assert_eq!(times.len(), 2);
+        assert_eq!(times[&0], CpuTimes { busy: 350, total: 1000 });
+        assert_eq!(times[&2], CpuTimes { busy: 150, total: 1000 });
+    }
+}
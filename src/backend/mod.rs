@@ -6,6 +6,16 @@ pub mod profile;
 pub mod thermal;
 pub mod turbo;
 pub mod auto_tune;
+pub mod pm_qos;
+pub mod rapl;
+pub mod energy;
+pub mod platform_profile;
+pub mod hwmon;
+pub mod thermal_protection;
+pub mod temperature_control;
+pub mod fan;
+pub mod load;
+pub mod throttle;
 
 pub use cpu::CpuManager;
 pub use frequency::FrequencyManager;
@@ -15,3 +25,12 @@ pub use profile::ProfileManager;
 pub use thermal::ThermalManager;
 pub use turbo::TurboManager;
 pub use auto_tune::AutoTuner;
+pub use pm_qos::PmQosManager;
+pub use rapl::RaplManager;
+pub use energy::EnergySampler;
+pub use platform_profile::PlatformProfileManager;
+pub use hwmon::HwmonManager;
+pub use thermal_protection::ThermalProtector;
+pub use temperature_control::TemperatureController;
+pub use fan::FanManager;
+pub use throttle::ThrottleMonitor;
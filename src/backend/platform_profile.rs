@@ -0,0 +1,90 @@
+// ACPI platform profile management module
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+const PLATFORM_PROFILE_PATH: &str = "/sys/firmware/acpi/platform_profile";
+const PLATFORM_PROFILE_CHOICES_PATH: &str = "/sys/firmware/acpi/platform_profile_choices";
+
+/// Closest alternatives for a requested profile, since vendors expose
+/// different subsets of the standard choices
+const PROFILE_FALLBACKS: &[(&str, &[&str])] = &[
+    ("quiet", &["low-power", "cool"]),
+    ("low-power", &["quiet", "cool"]),
+    ("cool", &["quiet", "low-power"]),
+    ("balanced", &["balanced-performance"]),
+    ("balanced-performance", &["performance", "balanced"]),
+    ("performance", &["balanced-performance"]),
+];
+
+pub struct PlatformProfileManager;
+
+impl PlatformProfileManager {
+    pub fn new() -> Result<Self> {
+        Ok(Self)
+    }
+
+    pub fn is_supported(&self) -> bool {
+        Path::new(PLATFORM_PROFILE_PATH).exists()
+    }
+
+    pub fn get_choices(&self) -> Result<Vec<String>> {
+        Ok(fs::read_to_string(PLATFORM_PROFILE_CHOICES_PATH)
+            .context("Failed to read platform profile choices")?
+            .split_whitespace()
+            .map(|s| s.to_string())
+            .collect())
+    }
+
+    pub fn get_profile(&self) -> Result<String> {
+        Ok(fs::read_to_string(PLATFORM_PROFILE_PATH)
+            .context("Failed to read platform profile")?
+            .trim()
+            .to_string())
+    }
+
+    /// Set the platform profile, falling back to the closest available choice
+    pub fn set_profile(&self, profile: &str) -> Result<()> {
+        let choices = self.get_choices()?;
+        let choice = resolve_choice(profile, &choices)
+            .ok_or_else(|| anyhow::anyhow!("Platform profile '{}' is not available. Available: {:?}", profile, choices))?;
+
+        if choice != profile {
+            log::warn!("Platform profile '{}' not available, using '{}'", profile, choice);
+        }
+
+        fs::write(PLATFORM_PROFILE_PATH, choice)
+            .context("Failed to set platform profile. Run with sudo or enable PolicyKit.")?;
+
+        log::info!("Set platform profile to {}", choice);
+        Ok(())
+    }
+}
+
+fn resolve_choice<'a>(requested: &str, choices: &'a [String]) -> Option<&'a str> {
+    let find = |name: &str| choices.iter().find(|c| c.as_str() == name).map(|c| c.as_str());
+
+    find(requested).or_else(|| {
+        PROFILE_FALLBACKS
+            .iter()
+            .find(|(name, _)| *name == requested)
+            .and_then(|(_, alternatives)| alternatives.iter().find_map(|alt| find(alt)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_choice() {
+        let choices: Vec<String> = ["low-power", "balanced", "performance"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        assert_eq!(resolve_choice("balanced", &choices), Some("balanced"));
+        assert_eq!(resolve_choice("quiet", &choices), Some("low-power"));
+        assert_eq!(resolve_choice("turbo", &choices), None);
+    }
+}
@@ -0,0 +1,62 @@
+// PM QoS (CPU latency) management module
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+
+const CPU_DMA_LATENCY_PATH: &str = "/dev/cpu_dma_latency";
+
+/// Holds a CPU latency request through `/dev/cpu_dma_latency`.
+///
+/// The kernel keeps the request active only while the file descriptor stays
+/// open, so the request is released by closing the file (explicitly via
+/// `release`, on drop, or when the process exits).
+pub struct PmQosManager {
+    request: Option<(File, u32)>,
+}
+
+impl PmQosManager {
+    pub fn new() -> Result<Self> {
+        Ok(Self { request: None })
+    }
+
+    /// Request a maximum CPU wakeup latency in microseconds, replacing any
+    /// request currently held.
+    pub fn hold_cpu_dma_latency(&mut self, latency_us: u32) -> Result<()> {
+        if self.current_latency() == Some(latency_us) {
+            return Ok(());
+        }
+
+        self.release();
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(CPU_DMA_LATENCY_PATH)
+            .with_context(|| format!("Failed to open {}. Run with sudo or enable PolicyKit.", CPU_DMA_LATENCY_PATH))?;
+
+        let value = i32::try_from(latency_us)
+            .context("CPU DMA latency value out of range")?;
+        file.write_all(&value.to_ne_bytes())
+            .context("Failed to write CPU DMA latency request")?;
+
+        log::info!("Holding CPU DMA latency request at {} us", latency_us);
+        self.request = Some((file, latency_us));
+        Ok(())
+    }
+
+    /// Drop the currently held latency request, if any.
+    pub fn release(&mut self) {
+        if let Some((_, latency_us)) = self.request.take() {
+            log::info!("Released CPU DMA latency request ({} us)", latency_us);
+        }
+    }
+
+    pub fn current_latency(&self) -> Option<u32> {
+        self.request.as_ref().map(|(_, latency_us)| *latency_us)
+    }
+}
+
+impl Drop for PmQosManager {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
@@ -1,22 +1,360 @@
 // Power management module
-use anyhow::Result;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
 
-pub struct PowerManager;
+const POWER_SUPPLY_BASE: &str = "/sys/class/power_supply";
+
+/// Power supply types that deliver external power (USB-C shows up as USB on newer kernels)
+const ADAPTER_TYPES: &[&str] = &["Mains", "USB", "USB_C", "USB_PD", "USB_PD_DRP"];
+
+/// Weight of the newest reading in the discharge rate moving average
+const DISCHARGE_SMOOTHING: f32 = 0.2;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatteryInfo {
+    pub name: String,
+    pub status: String,
+    pub capacity_percent: Option<u8>,
+    pub energy_now_wh: Option<f32>,
+    pub energy_full_wh: Option<f32>,
+    pub power_w: Option<f32>,
+    pub voltage_v: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChargeThresholds {
+    pub start: Option<u8>,
+    pub end: Option<u8>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatteryEstimate {
+    pub discharging: bool,
+    pub discharge_watts: Option<f32>,
+    pub time_remaining_secs: Option<u64>,
+}
+
+pub struct PowerManager {
+    base: PathBuf,
+}
 
 impl PowerManager {
     pub fn new() -> Result<Self> {
-        Ok(Self)
+        Ok(Self::from_base(Path::new(POWER_SUPPLY_BASE)))
     }
-    
+
+    pub(crate) fn from_base(base: &Path) -> Self {
+        Self { base: base.to_path_buf() }
+    }
+
+    /// Whether the system runs on external power. Any online adapter supply
+    /// (Mains, USB, USB-C/PD) counts; systems without a battery are desktops
+    /// and always on AC.
     pub fn is_on_ac_power(&self) -> Result<bool> {
-        // Check if system is on AC power
-        std::path::Path::new("/sys/class/power_supply/AC/online")
-            .exists()
-            .then(|| std::fs::read_to_string("/sys/class/power_supply/AC/online")
-                .ok()
-                .and_then(|s| s.trim().parse::<u8>().ok())
-                .map(|v| v == 1))
-            .flatten()
-            .ok_or_else(|| anyhow::anyhow!("Failed to determine power state"))
+        let adapters = self.get_adapters()?;
+        if adapters.iter().any(|(_, online)| *online) {
+            return Ok(true);
+        }
+
+        let batteries = self.get_batteries()?;
+        if batteries.is_empty() {
+            return Ok(true);
+        }
+
+        if adapters.is_empty() {
+            // No adapter is exposed, so fall back to what the batteries report
+            return Ok(!batteries.iter().any(|b| b.status == "Discharging"));
+        }
+
+        Ok(false)
+    }
+
+    /// External power supplies with their online state
+    pub fn get_adapters(&self) -> Result<Vec<(String, bool)>> {
+        Ok(self.supply_names()?
+            .into_iter()
+            .filter(|name| {
+                self.read_attr(name, "type")
+                    .is_some_and(|t| ADAPTER_TYPES.contains(&t.as_str()))
+            })
+            .filter(|name| self.read_attr(name, "scope").as_deref() != Some("Device"))
+            .map(|name| {
+                let online = self.read_attr(&name, "online").as_deref() == Some("1");
+                (name, online)
+            })
+            .collect())
+    }
+
+    fn supply_names(&self) -> Result<Vec<String>> {
+        let entries = fs::read_dir(&self.base)
+            .context("Failed to read power supply directory")?;
+
+        let mut names: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect();
+
+        names.sort();
+        Ok(names)
+    }
+
+    fn read_attr(&self, supply: &str, attr: &str) -> Option<String> {
+        fs::read_to_string(self.base.join(supply).join(attr))
+            .ok()
+            .map(|s| s.trim().to_string())
+    }
+
+    fn read_micro(&self, supply: &str, attr: &str) -> Option<f32> {
+        self.read_attr(supply, attr)?
+            .parse::<i64>()
+            .ok()
+            .map(|v| v.unsigned_abs() as f32 / 1_000_000.0)
+    }
+
+    /// System batteries, excluding peripheral (device-scoped) batteries
+    pub fn get_batteries(&self) -> Result<Vec<BatteryInfo>> {
+        Ok(self.supply_names()?
+            .into_iter()
+            .filter(|name| self.read_attr(name, "type").as_deref() == Some("Battery"))
+            .filter(|name| self.read_attr(name, "scope").as_deref() != Some("Device"))
+            .filter(|name| self.read_attr(name, "present").as_deref() != Some("0"))
+            .map(|name| self.get_battery_info(&name))
+            .collect())
+    }
+
+    pub fn get_battery_info(&self, name: &str) -> BatteryInfo {
+        let voltage_v = self.read_micro(name, "voltage_now");
+
+        // Batteries report either energy (uWh, uW) or charge (uAh, uA)
+        let power_w = self.read_micro(name, "power_now")
+            .or_else(|| Some(self.read_micro(name, "current_now")? * voltage_v?));
+        let energy_now_wh = self.read_micro(name, "energy_now")
+            .or_else(|| Some(self.read_micro(name, "charge_now")? * voltage_v?));
+        let energy_full_wh = self.read_micro(name, "energy_full")
+            .or_else(|| Some(self.read_micro(name, "charge_full")? * voltage_v?));
+
+        BatteryInfo {
+            name: name.to_string(),
+            status: self.read_attr(name, "status").unwrap_or_else(|| "Unknown".to_string()),
+            capacity_percent: self.read_attr(name, "capacity").and_then(|c| c.parse().ok()),
+            energy_now_wh,
+            energy_full_wh,
+            power_w,
+            voltage_v,
+        }
+    }
+
+    // Charge thresholds
+    pub fn get_charge_thresholds(&self, battery: &str) -> ChargeThresholds {
+        ChargeThresholds {
+            start: self.read_attr(battery, "charge_control_start_threshold").and_then(|v| v.parse().ok()),
+            end: self.read_attr(battery, "charge_control_end_threshold").and_then(|v| v.parse().ok()),
+        }
+    }
+
+    /// Batteries whose firmware exposes an end-of-charge threshold
+    pub fn get_threshold_batteries(&self) -> Result<Vec<String>> {
+        Ok(self.get_batteries()?
+            .into_iter()
+            .map(|b| b.name)
+            .filter(|name| self.base.join(name).join("charge_control_end_threshold").exists())
+            .collect())
+    }
+
+    pub fn set_charge_thresholds(&self, battery: &str, thresholds: ChargeThresholds) -> Result<()> {
+        let current = self.get_charge_thresholds(battery);
+        let start = thresholds.start.or(current.start);
+        let end = thresholds.end.or(current.end);
+
+        if let Some(value) = [thresholds.start, thresholds.end].into_iter().flatten().find(|&v| v > 100) {
+            anyhow::bail!("Invalid charge threshold: {}% (must be 0-100)", value);
+        }
+        if let (Some(start), Some(end)) = (start, end)
+            && start >= end
+        {
+            anyhow::bail!("Charge start threshold ({}%) must be below end threshold ({}%)", start, end);
+        }
+
+        // The kernel rejects a start above the current end (and vice versa),
+        // so raise the end first when moving the window up.
+        let raise_end_first = matches!((thresholds.start, current.end), (Some(s), Some(e)) if s >= e);
+        let writes = if raise_end_first {
+            [("charge_control_end_threshold", thresholds.end), ("charge_control_start_threshold", thresholds.start)]
+        } else {
+            [("charge_control_start_threshold", thresholds.start), ("charge_control_end_threshold", thresholds.end)]
+        };
+
+        for (attr, value) in writes {
+            let Some(value) = value else { continue };
+            fs::write(self.base.join(battery).join(attr), value.to_string())
+                .with_context(|| format!("Failed to set {} for {}. Run with sudo or enable PolicyKit.", attr, battery))?;
+        }
+
+        log::info!("Set charge thresholds for {}: start {:?}, end {:?}", battery, start, end);
+        Ok(())
+    }
+
+    /// Apply thresholds to every battery that supports them
+    pub fn set_charge_thresholds_all(&self, thresholds: ChargeThresholds) -> Result<()> {
+        let batteries = self.get_threshold_batteries()?;
+        if batteries.is_empty() {
+            anyhow::bail!("Charge thresholds are not supported on this system");
+        }
+
+        for battery in batteries {
+            self.set_charge_thresholds(&battery, thresholds)?;
+        }
+        Ok(())
+    }
+}
+
+/// Smooths battery discharge readings into a stable rate and time-remaining estimate
+#[derive(Default)]
+pub struct BatteryEstimator {
+    smoothed_watts: Option<f32>,
+}
+
+impl BatteryEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn update(&mut self, batteries: &[BatteryInfo]) -> BatteryEstimate {
+        let discharging = batteries.iter().any(|b| b.status == "Discharging");
+        if !discharging {
+            self.smoothed_watts = None;
+            return BatteryEstimate::default();
+        }
+
+        let watts: f32 = batteries
+            .iter()
+            .filter(|b| b.status == "Discharging")
+            .filter_map(|b| b.power_w)
+            .sum();
+
+        if watts > 0.0 {
+            self.smoothed_watts = Some(match self.smoothed_watts {
+                Some(previous) => previous + DISCHARGE_SMOOTHING * (watts - previous),
+                None => watts,
+            });
+        }
+
+        let energy_wh: f32 = batteries.iter().filter_map(|b| b.energy_now_wh).sum();
+        let time_remaining_secs = self.smoothed_watts
+            .filter(|&w| w > 0.0 && energy_wh > 0.0)
+            .map(|w| (energy_wh / w * 3600.0) as u64);
+
+        BatteryEstimate {
+            discharging,
+            discharge_watts: self.smoothed_watts,
+            time_remaining_secs,
+        }
+    }
+}
+
+impl BatteryEstimate {
+    pub fn format_time_remaining(&self) -> Option<String> {
+        let secs = self.time_remaining_secs?;
+        Some(format!("{}h {:02}m", secs / 3600, secs % 3600 / 60))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::TempDir;
+
+    fn battery(status: &str, power_w: f32, energy_now_wh: f32) -> BatteryInfo {
+        BatteryInfo {
+            name: "BAT0".to_string(),
+            status: status.to_string(),
+            capacity_percent: Some(50),
+            energy_now_wh: Some(energy_now_wh),
+            energy_full_wh: Some(2.0 * energy_now_wh),
+            power_w: Some(power_w),
+            voltage_v: Some(12.0),
+        }
+    }
+
+    #[test]
+    fn test_battery_from_charge_attributes() {
+        let base = TempDir::new("power");
+        let bat = base.join("BAT1");
+        fs::create_dir_all(&bat).unwrap();
+        fs::write(bat.join("type"), "Battery\n").unwrap();
+        fs::write(bat.join("status"), "Discharging\n").unwrap();
+        fs::write(bat.join("voltage_now"), "12000000\n").unwrap();
+        fs::write(bat.join("current_now"), "-500000\n").unwrap();
+        fs::write(bat.join("charge_now"), "2000000\n").unwrap();
+
+        let batteries = PowerManager::from_base(&base).get_batteries().unwrap();
+        assert_eq!(batteries.len(), 1);
+        assert_eq!(batteries[0].power_w, Some(6.0));
+        assert_eq!(batteries[0].energy_now_wh, Some(24.0));
+    }
+
+    fn write_supply(base: &Path, name: &str, attrs: &[(&str, &str)]) {
+        let dir = base.join(name);
+        fs::create_dir_all(&dir).unwrap();
+        for (attr, value) in attrs {
+            fs::write(dir.join(attr), format!("{}\n", value)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_ac_detection() {
+        let base = TempDir::new("ac");
+        let manager = PowerManager::from_base(&base);
+
+        // Desktop: only a peripheral battery, no adapters
+        write_supply(&base, "hidpp_battery_0", &[("type", "Battery"), ("scope", "Device"), ("status", "Discharging")]);
+        assert!(manager.is_on_ac_power().unwrap());
+
+        // Laptop on battery with a differently named adapter
+        write_supply(&base, "BAT0", &[("type", "Battery"), ("status", "Discharging")]);
+        write_supply(&base, "ADP1", &[("type", "Mains"), ("online", "0")]);
+        assert!(!manager.is_on_ac_power().unwrap());
+
+        // Plugged in through USB-C
+        write_supply(&base, "ucsi-source-psy-USBC000:001", &[("type", "USB"), ("online", "1")]);
+        assert!(manager.is_on_ac_power().unwrap());
+        assert_eq!(manager.get_adapters().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_charge_thresholds_write_order() {
+        let base = TempDir::new("thresholds");
+        write_supply(&base, "BAT0", &[
+            ("type", "Battery"),
+            ("charge_control_start_threshold", "40"),
+            ("charge_control_end_threshold", "60"),
+        ]);
+        let manager = PowerManager::from_base(&base);
+
+        let window = ChargeThresholds { start: Some(75), end: Some(80) };
+        manager.set_charge_thresholds_all(window).unwrap();
+        assert_eq!(manager.get_charge_thresholds("BAT0"), window);
+
+        let inverted = ChargeThresholds { start: Some(90), end: None };
+        assert!(manager.set_charge_thresholds("BAT0", inverted).is_err());
+    }
+
+    #[test]
+    fn test_battery_estimator_smoothing() {
+        let mut estimator = BatteryEstimator::new();
+
+        let estimate = estimator.update(&[battery("Discharging", 10.0, 50.0)]);
+        assert_eq!(estimate.discharge_watts, Some(10.0));
+        assert_eq!(estimate.time_remaining_secs, Some(18_000));
+        assert_eq!(estimate.format_time_remaining().as_deref(), Some("5h 00m"));
+
+        let estimate = estimator.update(&[battery("Discharging", 20.0, 50.0)]);
+        assert_eq!(estimate.discharge_watts, Some(12.0));
+
+        let estimate = estimator.update(&[battery("Charging", 20.0, 50.0)]);
+        assert!(!estimate.discharging);
+        assert_eq!(estimate.discharge_watts, None);
     }
 }
@@ -1,6 +1,8 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use crate::backend::cpu::CpuManager;
+use crate::backend::fan::FanCurve;
+use crate::backend::{PlatformProfileManager, RaplManager};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Profile {
@@ -14,6 +16,49 @@ pub struct Profile {
     pub epp: Option<String>,
     #[serde(default)]
     pub epb: Option<u8>,
+    /// ACPI platform profile (e.g. "low-power", "balanced", "performance", "quiet")
+    #[serde(default)]
+    pub platform_profile: Option<String>,
+    /// CPU wakeup latency (us) held through `/dev/cpu_dma_latency` while the profile is active
+    #[serde(default)]
+    pub cpu_dma_latency_us: Option<u32>,
+    /// Package long-term power limit (PL1) in watts
+    #[serde(default)]
+    pub package_pl1_watts: Option<f32>,
+    /// Package short-term power limit (PL2) in watts
+    #[serde(default)]
+    pub package_pl2_watts: Option<f32>,
+    /// Temperature the service holds by lowering the max frequency below `max_freq_mhz`, see `[thermal.pid]`
+    #[serde(default)]
+    pub target_temp_celsius: Option<f32>,
+    /// Fan curve applied by the service when `fan_control_enabled` is set
+    #[serde(default)]
+    pub fan_curve: Option<FanCurve>,
+}
+
+/// Firmware settings a profile can change besides cpufreq. Discovered once by
+/// whoever applies profiles; either may be missing on a given machine.
+pub struct FirmwareControls {
+    rapl: Option<RaplManager>,
+    platform_profile: Option<PlatformProfileManager>,
+}
+
+impl FirmwareControls {
+    pub fn new() -> Self {
+        let rapl = RaplManager::new()
+            .map_err(|e| log::debug!("RAPL power limits not supported: {}", e))
+            .ok();
+        let platform_profile = PlatformProfileManager::new()
+            .ok()
+            .filter(PlatformProfileManager::is_supported);
+        Self { rapl, platform_profile }
+    }
+}
+
+impl Default for FirmwareControls {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -35,6 +80,12 @@ impl Profile {
             max_freq_mhz: None,
             epp: Some("performance".to_string()),
             epb: Some(0),
+            platform_profile: Some("performance".to_string()),
+            cpu_dma_latency_us: None,
+            package_pl1_watts: None,
+            package_pl2_watts: None,
+            target_temp_celsius: None,
+            fan_curve: None,
         }
     }
 
@@ -48,6 +99,12 @@ impl Profile {
             max_freq_mhz: None,
             epp: Some("balance_performance".to_string()),
             epb: Some(6),
+            platform_profile: Some("balanced".to_string()),
+            cpu_dma_latency_us: None,
+            package_pl1_watts: None,
+            package_pl2_watts: None,
+            target_temp_celsius: None,
+            fan_curve: None,
         }
     }
 
@@ -61,6 +118,12 @@ impl Profile {
             max_freq_mhz: Some(2400),
             epp: Some("power".to_string()),
             epb: Some(15),
+            platform_profile: Some("low-power".to_string()),
+            cpu_dma_latency_us: None,
+            package_pl1_watts: None,
+            package_pl2_watts: None,
+            target_temp_celsius: None,
+            fan_curve: None,
         }
     }
 
@@ -74,10 +137,16 @@ impl Profile {
             max_freq_mhz: Some(2000),
             epp: Some("power".to_string()),
             epb: Some(15),
+            platform_profile: Some("quiet".to_string()),
+            cpu_dma_latency_us: None,
+            package_pl1_watts: None,
+            package_pl2_watts: None,
+            target_temp_celsius: Some(70.0),
+            fan_curve: Some(FanCurve::quiet()),
         }
     }
 
-    pub fn apply(&self, cpu_manager: &CpuManager) -> Result<()> {
+    pub fn apply(&self, cpu_manager: &CpuManager, firmware: &FirmwareControls) -> Result<()> {
         log::info!("Applying profile: {}", self.name);
 
         // Get available governors to ensure compatibility
@@ -151,10 +220,48 @@ impl Profile {
             }
         }
 
+        // Set ACPI platform profile if supported and specified
+        if let Some(ref platform_profile) = self.platform_profile {
+            match &firmware.platform_profile {
+                Some(manager) => {
+                    if let Err(e) = manager.set_profile(platform_profile) {
+                        log::warn!("Failed to set platform profile to {}: {}", platform_profile, e);
+                    }
+                }
+                None => log::debug!("Platform profile not supported, skipping '{}'", platform_profile),
+            }
+        }
+
+        if let Some(rapl) = &firmware.rapl {
+            self.apply_power_limits(rapl);
+        } else if self.package_pl1_watts.is_some() || self.package_pl2_watts.is_some() {
+            log::warn!("Package power limits not set: RAPL is not supported");
+        }
+
         log::info!("Profile '{}' applied successfully", self.name);
         Ok(())
     }
 
+    /// Put back limits an earlier profile replaced, then set this profile's
+    /// RAPL package power limits, so limits never stick across profiles
+    fn apply_power_limits(&self, rapl: &RaplManager) {
+        if let Err(e) = rapl.restore_power_limits() {
+            log::warn!("Failed to restore package power limits: {}", e);
+        }
+
+        if let Some(pl1) = self.package_pl1_watts
+            && let Err(e) = rapl.set_package_pl1(pl1)
+        {
+            log::warn!("Failed to set PL1 to {} W: {}", pl1, e);
+        }
+
+        if let Some(pl2) = self.package_pl2_watts
+            && let Err(e) = rapl.set_package_pl2(pl2)
+        {
+            log::warn!("Failed to set PL2 to {} W: {}", pl2, e);
+        }
+    }
+
     /// Select the best available governor based on what's requested and what's available
     fn select_best_governor(&self, available: &[String]) -> Result<&str> {
         // If requested governor is available, use it
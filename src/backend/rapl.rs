@@ -0,0 +1,340 @@
+// Intel RAPL (powercap) management module
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const POWERCAP_BASE: &str = "/sys/class/powercap";
+const RAPL_ZONE_PREFIX: &str = "intel-rapl:";
+
+/// Limits as they were before our first write, keyed by `<zone>/<constraint>`.
+/// Kept under /run since firmware restores its own limits on reboot.
+const SAVED_LIMITS_PATH: &str = "/run/cpu-power-manager/rapl-limits.json";
+
+/// Constraint names used by the kernel for PL1 and PL2
+const PL1_CONSTRAINT: &str = "long_term";
+const PL2_CONSTRAINT: &str = "short_term";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RaplZoneKind {
+    Package,
+    Core,
+    Uncore,
+    Dram,
+    Psys,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RaplZone {
+    pub id: String,
+    pub name: String,
+    pub kind: RaplZoneKind,
+    pub enabled: bool,
+    pub energy_uj: u64,
+    pub max_energy_range_uj: u64,
+    pub constraints: Vec<RaplConstraint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RaplConstraint {
+    pub id: usize,
+    pub name: String,
+    pub power_limit_uw: u64,
+    pub time_window_us: Option<u64>,
+    pub max_power_uw: Option<u64>,
+}
+
+pub struct RaplManager {
+    zones: Vec<PathBuf>,
+    saved_limits: PathBuf,
+}
+
+impl RaplManager {
+    pub fn new() -> Result<Self> {
+        let manager = Self::from_base(Path::new(POWERCAP_BASE), Path::new(SAVED_LIMITS_PATH))?;
+        log::info!("Discovered {} RAPL zones", manager.zones.len());
+        Ok(manager)
+    }
+
+    fn from_base(base: &Path, saved_limits: &Path) -> Result<Self> {
+        let entries = fs::read_dir(base)
+            .context("Failed to read powercap directory")?;
+
+        let mut zones: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().starts_with(RAPL_ZONE_PREFIX))
+            .map(|e| e.path())
+            .collect();
+
+        zones.sort();
+        Ok(Self { zones, saved_limits: saved_limits.to_path_buf() })
+    }
+
+    pub fn get_zone_count(&self) -> usize {
+        self.zones.len()
+    }
+
+    fn zone_path(&self, zone: usize) -> Result<&Path> {
+        self.zones
+            .get(zone)
+            .map(|p| p.as_path())
+            .ok_or_else(|| anyhow::anyhow!("RAPL zone {} does not exist", zone))
+    }
+
+    pub fn get_zone_name(&self, zone: usize) -> Result<String> {
+        let path = self.zone_path(zone)?.join("name");
+        Ok(fs::read_to_string(&path)
+            .context("Failed to read RAPL zone name")?
+            .trim()
+            .to_string())
+    }
+
+    pub fn get_zone_kind(&self, zone: usize) -> Result<RaplZoneKind> {
+        let name = self.get_zone_name(zone)?;
+        Ok(RaplZoneKind::from_name(&name))
+    }
+
+    pub fn energy_counter_path(&self, zone: usize) -> Result<PathBuf> {
+        Ok(self.zone_path(zone)?.join("energy_uj"))
+    }
+
+    pub fn read_energy_uj(&self, zone: usize) -> Result<u64> {
+        read_u64(&self.energy_counter_path(zone)?)
+            .context("Failed to read RAPL energy counter")
+    }
+
+    pub fn read_max_energy_range_uj(&self, zone: usize) -> Result<u64> {
+        read_u64(&self.zone_path(zone)?.join("max_energy_range_uj"))
+            .context("Failed to read RAPL energy range")
+    }
+
+    pub fn get_constraints(&self, zone: usize) -> Result<Vec<RaplConstraint>> {
+        let path = self.zone_path(zone)?;
+        let mut constraints = vec![];
+        let mut id = 0;
+
+        loop {
+            let limit_path = path.join(format!("constraint_{}_power_limit_uw", id));
+            if !limit_path.exists() {
+                break;
+            }
+
+            let name = fs::read_to_string(path.join(format!("constraint_{}_name", id)))
+                .unwrap_or_else(|_| "unknown".to_string())
+                .trim()
+                .to_string();
+
+            constraints.push(RaplConstraint {
+                id,
+                name,
+                power_limit_uw: read_u64(&limit_path).context("Failed to read RAPL power limit")?,
+                time_window_us: read_u64(&path.join(format!("constraint_{}_time_window_us", id))).ok(),
+                max_power_uw: read_u64(&path.join(format!("constraint_{}_max_power_uw", id))).ok(),
+            });
+
+            id += 1;
+        }
+
+        Ok(constraints)
+    }
+
+    pub fn get_zone_info(&self, zone: usize) -> Result<RaplZone> {
+        let path = self.zone_path(zone)?;
+        let name = self.get_zone_name(zone)?;
+
+        Ok(RaplZone {
+            id: path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+            kind: RaplZoneKind::from_name(&name),
+            name,
+            enabled: read_u64(&path.join("enabled")).map(|v| v == 1).unwrap_or(true),
+            energy_uj: self.read_energy_uj(zone)?,
+            max_energy_range_uj: self.read_max_energy_range_uj(zone)?,
+            constraints: self.get_constraints(zone)?,
+        })
+    }
+
+    pub fn get_all_zones(&self) -> Result<Vec<RaplZone>> {
+        (0..self.zones.len())
+            .map(|zone| self.get_zone_info(zone))
+            .collect()
+    }
+
+    pub fn set_power_limit(&self, zone: usize, constraint: usize, watts: f32) -> Result<()> {
+        if !watts.is_finite() || watts <= 0.0 {
+            anyhow::bail!("Invalid power limit: {} W", watts);
+        }
+
+        let path = self.zone_path(zone)?.join(format!("constraint_{}_power_limit_uw", constraint));
+        let power_uw = (watts as f64 * 1_000_000.0).round() as u64;
+        self.save_original_limit(zone, constraint)?;
+
+        fs::write(&path, power_uw.to_string())
+            .with_context(|| format!("Failed to set power limit for RAPL zone {}. Run with sudo or enable PolicyKit.", zone))?;
+
+        log::info!("Set RAPL zone {} constraint {} power limit to {:.1} W", zone, constraint, watts);
+        Ok(())
+    }
+
+    fn limit_key(&self, zone: usize, constraint: usize) -> Result<String> {
+        let name = self.zone_path(zone)?.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        Ok(format!("{}/{}", name, constraint))
+    }
+
+    fn load_saved_limits(&self) -> BTreeMap<String, u64> {
+        fs::read_to_string(&self.saved_limits)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// Remember a limit before it is first overwritten
+    fn save_original_limit(&self, zone: usize, constraint: usize) -> Result<()> {
+        let key = self.limit_key(zone, constraint)?;
+        let mut saved = self.load_saved_limits();
+        if saved.contains_key(&key) {
+            return Ok(());
+        }
+
+        let path = self.zone_path(zone)?.join(format!("constraint_{}_power_limit_uw", constraint));
+        saved.insert(key, read_u64(&path).context("Failed to read RAPL power limit")?);
+        if let Some(dir) = self.saved_limits.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.saved_limits, serde_json::to_string(&saved)?)
+            .with_context(|| format!("Failed to save original power limits to {}", self.saved_limits.display()))
+    }
+
+    /// Put back every limit changed since boot. Does nothing if none were.
+    pub fn restore_power_limits(&self) -> Result<()> {
+        let saved = self.load_saved_limits();
+        for (key, power_uw) in &saved {
+            let Some((name, constraint)) = key.split_once('/') else {
+                continue;
+            };
+            let Some(zone) = self.zones.iter().find(|zone| zone.file_name().is_some_and(|n| n == name)) else {
+                continue;
+            };
+
+            fs::write(zone.join(format!("constraint_{}_power_limit_uw", constraint)), power_uw.to_string())
+                .with_context(|| format!("Failed to restore power limit for RAPL zone {}", name))?;
+            log::info!("Restored RAPL {} constraint {} power limit to {:.1} W", name, constraint, *power_uw as f64 / 1_000_000.0);
+        }
+
+        if !saved.is_empty() {
+            fs::remove_file(&self.saved_limits)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_time_window(&self, zone: usize, constraint: usize, time_window_us: u64) -> Result<()> {
+        let path = self.zone_path(zone)?.join(format!("constraint_{}_time_window_us", constraint));
+
+        fs::write(&path, time_window_us.to_string())
+            .with_context(|| format!("Failed to set time window for RAPL zone {}. Run with sudo or enable PolicyKit.", zone))?;
+
+        log::info!("Set RAPL zone {} constraint {} time window to {} us", zone, constraint, time_window_us);
+        Ok(())
+    }
+
+    /// Set the long-term (PL1) limit on every package zone
+    pub fn set_package_pl1(&self, watts: f32) -> Result<()> {
+        self.set_package_limit(PL1_CONSTRAINT, watts)
+    }
+
+    /// Set the short-term (PL2) limit on every package zone
+    pub fn set_package_pl2(&self, watts: f32) -> Result<()> {
+        self.set_package_limit(PL2_CONSTRAINT, watts)
+    }
+
+    fn set_package_limit(&self, constraint_name: &str, watts: f32) -> Result<()> {
+        let mut applied = false;
+
+        for zone in 0..self.zones.len() {
+            if self.get_zone_kind(zone)? != RaplZoneKind::Package {
+                continue;
+            }
+
+            let constraint = self.get_constraints(zone)?
+                .into_iter()
+                .find(|c| c.name == constraint_name)
+                .ok_or_else(|| anyhow::anyhow!("RAPL zone {} has no {} constraint", zone, constraint_name))?;
+
+            self.set_power_limit(zone, constraint.id, watts)?;
+            applied = true;
+        }
+
+        if !applied {
+            anyhow::bail!("No RAPL package zones found");
+        }
+        Ok(())
+    }
+}
+
+impl RaplZoneKind {
+    fn from_name(name: &str) -> Self {
+        match name {
+            n if n.starts_with("package") => RaplZoneKind::Package,
+            "core" => RaplZoneKind::Core,
+            "uncore" => RaplZoneKind::Uncore,
+            "dram" => RaplZoneKind::Dram,
+            "psys" => RaplZoneKind::Psys,
+            _ => RaplZoneKind::Unknown,
+        }
+    }
+}
+
+fn read_u64(path: &Path) -> Result<u64> {
+    Ok(fs::read_to_string(path)?.trim().parse()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::TempDir;
+
+    fn write_zone(base: &Path, id: &str, name: &str) -> PathBuf {
+        let zone = base.join(id);
+        fs::create_dir_all(&zone).unwrap();
+        fs::write(zone.join("name"), format!("{}\n", name)).unwrap();
+        fs::write(zone.join("energy_uj"), "123456\n").unwrap();
+        fs::write(zone.join("max_energy_range_uj"), "262143328850\n").unwrap();
+        fs::write(zone.join("constraint_0_name"), "long_term\n").unwrap();
+        fs::write(zone.join("constraint_0_power_limit_uw"), "15000000\n").unwrap();
+        fs::write(zone.join("constraint_0_time_window_us"), "27983872\n").unwrap();
+        fs::write(zone.join("constraint_1_name"), "short_term\n").unwrap();
+        fs::write(zone.join("constraint_1_power_limit_uw"), "25000000\n").unwrap();
+        zone
+    }
+
+    #[test]
+    fn test_rapl_zones_and_package_limits() {
+        let base = TempDir::new("rapl");
+        let package = write_zone(&base, "intel-rapl:0", "package-0");
+        write_zone(&base, "intel-rapl:0:0", "core");
+        fs::create_dir_all(base.join("intel-rapl")).unwrap();
+
+        let saved_limits = base.join("saved-limits.json");
+        let manager = RaplManager::from_base(&base, &saved_limits).unwrap();
+        assert_eq!(manager.get_zone_count(), 2);
+
+        let zones = manager.get_all_zones().unwrap();
+        assert_eq!(zones[0].kind, RaplZoneKind::Package);
+        assert_eq!(zones[1].kind, RaplZoneKind::Core);
+        assert_eq!(zones[0].constraints[0].time_window_us, Some(27983872));
+        assert_eq!(zones[0].constraints[1].time_window_us, None);
+
+        manager.set_package_pl1(28.5).unwrap();
+        manager.set_package_pl2(40.0).unwrap();
+        assert_eq!(fs::read_to_string(package.join("constraint_0_power_limit_uw")).unwrap(), "28500000");
+        assert_eq!(fs::read_to_string(package.join("constraint_1_power_limit_uw")).unwrap(), "40000000");
+
+        // The firmware value is kept across repeated writes
+        manager.set_package_pl1(20.0).unwrap();
+        manager.restore_power_limits().unwrap();
+        assert_eq!(fs::read_to_string(package.join("constraint_0_power_limit_uw")).unwrap(), "15000000");
+        assert_eq!(fs::read_to_string(package.join("constraint_1_power_limit_uw")).unwrap(), "25000000");
+        assert!(!saved_limits.exists());
+    }
+}
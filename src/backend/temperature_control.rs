@@ -0,0 +1,164 @@
+// Closed-loop temperature control module
+use anyhow::Result;
+use std::time::Instant;
+
+use crate::backend::CpuManager;
+use crate::config::PidConfig;
+
+/// Changes smaller than this are not written to sysfs
+const FREQ_DEADBAND_MHZ: u32 = 25;
+
+/// PID controller whose output is a frequency cap. A positive error (too hot)
+/// lowers the output from the top of its range.
+pub struct PidController {
+    gains: PidConfig,
+    min_output: f32,
+    max_output: f32,
+    integral: f32,
+    last_error: Option<f32>,
+}
+
+impl PidController {
+    pub fn new(gains: PidConfig, min_output: f32, max_output: f32) -> Self {
+        Self {
+            gains,
+            min_output,
+            max_output,
+            integral: 0.0,
+            last_error: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.integral = 0.0;
+        self.last_error = None;
+    }
+
+    /// Advance the controller by `dt` seconds and return the new output
+    pub fn update(&mut self, setpoint: f32, measured: f32, dt: f32) -> f32 {
+        let error = measured - setpoint;
+        let derivative = match self.last_error {
+            Some(last) if dt > 0.0 => (error - last) / dt,
+            _ => 0.0,
+        };
+        self.last_error = Some(error);
+
+        let integral = self.integral + error * dt;
+        let correction = self.gains.kp * error + self.gains.ki * integral + self.gains.kd * derivative;
+        let output = self.max_output - correction;
+
+        // Anti-windup: stop integrating while saturated in the direction of the error
+        let saturated_low = output < self.min_output && error > 0.0;
+        let saturated_high = output > self.max_output && error < 0.0;
+        if !saturated_low && !saturated_high {
+            self.integral = integral;
+        }
+
+        output.clamp(self.min_output, self.max_output)
+    }
+}
+
+/// Holds the CPU at a temperature setpoint by adjusting `scaling_max_freq`
+/// on all online cores between the hardware minimum and the profile's static cap
+/// (or the hardware maximum if it has none).
+pub struct TemperatureController {
+    setpoint: f32,
+    pid: PidController,
+    last_update: Option<Instant>,
+    current_cap_mhz: Option<u32>,
+}
+
+impl TemperatureController {
+    pub fn new(setpoint: f32, max_freq_mhz: Option<u32>, gains: PidConfig, cpu_manager: &CpuManager) -> Result<Self> {
+        let hw_min = cpu_manager.get_hardware_min_freq(0)?;
+        let hw_max = cpu_manager.get_hardware_max_freq(0)?;
+        let ceiling = max_freq_mhz.map_or(hw_max, |cap| cap.clamp(hw_min, hw_max));
+        log::info!("Holding CPU at {:.1}°C ({} - {} MHz)", setpoint, hw_min, ceiling);
+
+        Ok(Self {
+            setpoint,
+            pid: PidController::new(gains, hw_min as f32, ceiling as f32),
+            last_update: None,
+            current_cap_mhz: None,
+        })
+    }
+
+    pub fn setpoint(&self) -> f32 {
+        self.setpoint
+    }
+
+    pub fn current_cap_mhz(&self) -> Option<u32> {
+        self.current_cap_mhz
+    }
+
+    /// Feed a new temperature reading and apply the resulting frequency cap
+    pub fn update(&mut self, temp: f32, cpu_manager: &CpuManager) -> Result<()> {
+        let now = Instant::now();
+        let dt = self.last_update.map(|last| now.duration_since(last).as_secs_f32()).unwrap_or(0.0);
+        self.last_update = Some(now);
+
+        let cap_mhz = self.pid.update(self.setpoint, temp, dt).round() as u32;
+        if let Some(current) = self.current_cap_mhz
+            && current.abs_diff(cap_mhz) < FREQ_DEADBAND_MHZ
+        {
+            return Ok(());
+        }
+
+        log::debug!("Temperature control: {:.1}°C (target {:.1}°C), max frequency {} MHz", temp, self.setpoint, cap_mhz);
+        for core in cpu_manager.online_cores() {
+            cpu_manager.set_scaling_max_freq(core, cap_mhz)?;
+        }
+        self.current_cap_mhz = Some(cap_mhz);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::{write_cpus, TempDir};
+    use std::fs;
+
+    #[test]
+    fn test_pid_tracks_setpoint_with_anti_windup() {
+        let gains = PidConfig { kp: 100.0, ki: 10.0, kd: 0.0 };
+        let mut pid = PidController::new(gains, 800.0, 4000.0);
+
+        // Cool CPU: output stays at the maximum and no negative integral builds up
+        for _ in 0..100 {
+            assert_eq!(pid.update(70.0, 50.0, 1.0), 4000.0);
+        }
+        assert_eq!(pid.integral, 0.0);
+
+        // Hot CPU pulls the cap down immediately, clamped at the minimum
+        let output = pid.update(70.0, 80.0, 1.0);
+        assert!(output < 4000.0);
+        for _ in 0..100 {
+            assert_eq!(pid.update(70.0, 100.0, 1.0), 800.0);
+        }
+
+        // The integral stayed bounded, so recovery starts as soon as it cools
+        assert!(pid.update(70.0, 60.0, 1.0) > 800.0);
+    }
+
+    #[test]
+    fn test_offline_cores_are_skipped() {
+        if !nix::unistd::Uid::effective().is_root() {
+            eprintln!("writing limits needs root, skipping");
+            return;
+        }
+
+        let base = TempDir::new("temperature-control");
+        write_cpus(&base, &[true, false, true]);
+        let cpu_manager = CpuManager::from_base(&base).unwrap();
+        let gains = PidConfig { kp: 100.0, ki: 0.0, kd: 0.0 };
+        let mut controller = TemperatureController::new(70.0, Some(3000), gains, &cpu_manager).unwrap();
+
+        controller.update(75.0, &cpu_manager).unwrap();
+        assert_eq!(controller.current_cap_mhz(), Some(2500));
+        for core in [0, 2] {
+            let max = fs::read_to_string(base.join(format!("cpu{}/cpufreq/scaling_max_freq", core))).unwrap();
+            assert_eq!(max, "2500000");
+        }
+    }
+}
@@ -1,7 +1,13 @@
 use anyhow::{Context, Result};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::backend::HwmonManager;
+use crate::backend::hwmon::TemperatureReading;
 
 const THERMAL_BASE: &str = "/sys/class/thermal";
 
@@ -10,7 +16,10 @@ pub struct ThermalZone {
     pub id: usize,
     pub type_name: String,
     pub temp_celsius: f32,
+    /// Thermal governor in charge of the zone (step_wise, power_allocator, ...)
+    pub policy: Option<String>,
     pub trip_points: Vec<TripPoint>,
+    pub bindings: Vec<CoolingBinding>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,31 +29,82 @@ pub struct TripPoint {
     pub trip_type: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoolingDevice {
+    pub id: usize,
+    pub type_name: String,
+    pub cur_state: u64,
+    pub max_state: u64,
+}
+
+/// A cooling device a zone can throttle, and the trip point that engages it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoolingBinding {
+    pub cooling_device: usize,
+    pub trip_point: Option<usize>,
+    pub weight: Option<u32>,
+}
+
+/// Where the CPU temperature is read from.
+/// Written in config as `auto`, `hwmon:<chip>/<label>` or `zone:<type>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum CpuTempSource {
+    #[default]
+    Auto,
+    Hwmon { chip: String, label: String },
+    Zone { type_name: String },
+}
+
 pub struct ThermalManager {
-    zones: Vec<PathBuf>,
+    /// Keyed by the kernel's id, the N in `thermal_zoneN`
+    zones: BTreeMap<usize, PathBuf>,
+    /// Keyed by the N in `cooling_deviceN`, which zone bindings refer to
+    cooling_devices: BTreeMap<usize, PathBuf>,
+    hwmon: HwmonManager,
+    cpu_temp_source: CpuTempSource,
 }
 
 impl ThermalManager {
     pub fn new() -> Result<Self> {
-        let zones = Self::discover_thermal_zones()?;
-        log::info!("Discovered {} thermal zones", zones.len());
-        Ok(Self { zones })
+        let manager = Self::from_base(Path::new(THERMAL_BASE))?;
+        log::info!("Discovered {} thermal zones and {} cooling devices", manager.zones.len(), manager.cooling_devices.len());
+        Ok(manager)
     }
 
-    fn discover_thermal_zones() -> Result<Vec<PathBuf>> {
-        let entries = fs::read_dir(THERMAL_BASE)
+    pub(crate) fn from_base(base: &Path) -> Result<Self> {
+        Ok(Self {
+            zones: Self::discover(base, "thermal_zone")?,
+            cooling_devices: Self::discover(base, "cooling_device")?,
+            hwmon: HwmonManager::new()?,
+            cpu_temp_source: CpuTempSource::Auto,
+        })
+    }
+
+    pub fn set_cpu_temp_source(&mut self, source: CpuTempSource) {
+        log::info!("CPU temperature source: {}", source);
+        self.cpu_temp_source = source;
+    }
+
+    /// Entries named `<prefix>N`, keyed by N. The kernel's numbering can have gaps.
+    fn discover(base: &Path, prefix: &str) -> Result<BTreeMap<usize, PathBuf>> {
+        let entries = fs::read_dir(base)
             .context("Failed to read thermal directory")?;
-        
-        let mut zones = vec![];
-        for entry in entries.filter_map(|e| e.ok()) {
-            let name = entry.file_name();
-            if name.to_string_lossy().starts_with("thermal_zone") {
-                zones.push(entry.path());
-            }
-        }
-        
-        zones.sort();
-        Ok(zones)
+
+        Ok(entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let id = e.file_name().to_str()?.strip_prefix(prefix)?.parse().ok()?;
+                Some((id, e.path()))
+            })
+            .collect())
+    }
+
+    fn zone_path(&self, zone: usize) -> Result<&Path> {
+        self.zones
+            .get(&zone)
+            .map(PathBuf::as_path)
+            .ok_or_else(|| anyhow::anyhow!("Thermal zone {} does not exist", zone))
     }
 
     pub fn get_zone_count(&self) -> usize {
@@ -52,11 +112,7 @@ impl ThermalManager {
     }
 
     pub fn get_temperature(&self, zone: usize) -> Result<f32> {
-        if zone >= self.zones.len() {
-            anyhow::bail!("Thermal zone {} does not exist", zone);
-        }
-
-        let temp_path = self.zones[zone].join("temp");
+        let temp_path = self.zone_path(zone)?.join("temp");
         let temp_millicelsius: i32 = fs::read_to_string(&temp_path)
             .context("Failed to read temperature")?
             .trim()
@@ -67,17 +123,14 @@ impl ThermalManager {
     }
 
     pub fn get_all_temperatures(&self) -> Result<Vec<f32>> {
-        (0..self.zones.len())
-            .map(|zone| self.get_temperature(zone))
+        self.zones
+            .keys()
+            .map(|&zone| self.get_temperature(zone))
             .collect()
     }
 
     pub fn get_zone_type(&self, zone: usize) -> Result<String> {
-        if zone >= self.zones.len() {
-            anyhow::bail!("Thermal zone {} does not exist", zone);
-        }
-
-        let type_path = self.zones[zone].join("type");
+        let type_path = self.zone_path(zone)?.join("type");
         Ok(fs::read_to_string(&type_path)
             .context("Failed to read zone type")?
             .trim()
@@ -93,23 +146,33 @@ impl ThermalManager {
             id: zone,
             type_name,
             temp_celsius,
+            policy: self.get_zone_policy(zone).ok(),
             trip_points,
+            bindings: self.get_zone_bindings(zone)?,
         })
     }
 
-    pub fn get_all_zones(&self) -> Result<Vec<ThermalZone>> {
-        (0..self.zones.len())
-            .map(|zone| self.get_zone_info(zone))
+    /// Zones that can be read. Some drivers (iwlwifi while the radio is off)
+    /// fail with ENODATA; those are skipped rather than hiding every zone.
+    pub fn get_all_zones(&self) -> Vec<ThermalZone> {
+        self.zones
+            .keys()
+            .filter_map(|&zone| {
+                self.get_zone_info(zone)
+                    .map_err(|e| log::debug!("Skipping thermal zone {}: {:#}", zone, e))
+                    .ok()
+            })
             .collect()
     }
 
-    fn get_trip_points(&self, zone: usize) -> Result<Vec<TripPoint>> {
+    pub fn get_trip_points(&self, zone: usize) -> Result<Vec<TripPoint>> {
+        let path = self.zone_path(zone)?;
         let mut trip_points = vec![];
         let mut trip_id = 0;
 
         loop {
-            let temp_path = self.zones[zone].join(format!("trip_point_{}_temp", trip_id));
-            let type_path = self.zones[zone].join(format!("trip_point_{}_type", trip_id));
+            let temp_path = path.join(format!("trip_point_{}_temp", trip_id));
+            let type_path = path.join(format!("trip_point_{}_type", trip_id));
 
             if !temp_path.exists() {
                 break;
@@ -138,6 +201,79 @@ impl ThermalManager {
         Ok(trip_points)
     }
 
+    pub fn get_zone_policy(&self, zone: usize) -> Result<String> {
+        Ok(fs::read_to_string(self.zone_path(zone)?.join("policy"))
+            .context("Failed to read zone policy")?
+            .trim()
+            .to_string())
+    }
+
+    /// Cooling devices bound to a zone through its `cdevN` links
+    pub fn get_zone_bindings(&self, zone: usize) -> Result<Vec<CoolingBinding>> {
+        let path = self.zone_path(zone)?;
+        let read_attr = |name: String| -> Option<String> {
+            fs::read_to_string(path.join(name)).ok().map(|v| v.trim().to_string())
+        };
+
+        let mut bindings = vec![];
+        let mut index = 0;
+        while let Ok(target) = fs::read_link(path.join(format!("cdev{}", index))) {
+            let cooling_device = target
+                .file_name()
+                .and_then(|name| name.to_str()?.strip_prefix("cooling_device")?.parse().ok());
+
+            if let Some(cooling_device) = cooling_device {
+                bindings.push(CoolingBinding {
+                    cooling_device,
+                    trip_point: read_attr(format!("cdev{}_trip_point", index)).and_then(|t| t.parse().ok()),
+                    weight: read_attr(format!("cdev{}_weight", index)).and_then(|w| w.parse().ok()),
+                });
+            }
+            index += 1;
+        }
+
+        Ok(bindings)
+    }
+
+    pub fn get_cooling_device_count(&self) -> usize {
+        self.cooling_devices.len()
+    }
+
+    pub fn get_cooling_device(&self, device: usize) -> Result<CoolingDevice> {
+        let path = self.cooling_devices.get(&device)
+            .ok_or_else(|| anyhow::anyhow!("Cooling device {} does not exist", device))?;
+
+        let read_state = |name: &str| -> Result<u64> {
+            fs::read_to_string(path.join(name))
+                .with_context(|| format!("Failed to read cooling device {} {}", device, name))?
+                .trim()
+                .parse()
+                .with_context(|| format!("Failed to parse cooling device {} {}", device, name))
+        };
+
+        Ok(CoolingDevice {
+            id: device,
+            type_name: fs::read_to_string(path.join("type"))
+                .context("Failed to read cooling device type")?
+                .trim()
+                .to_string(),
+            cur_state: read_state("cur_state")?,
+            max_state: read_state("max_state")?,
+        })
+    }
+
+    /// Cooling devices that can be read, skipping any whose driver fails
+    pub fn get_all_cooling_devices(&self) -> Vec<CoolingDevice> {
+        self.cooling_devices
+            .keys()
+            .filter_map(|&device| {
+                self.get_cooling_device(device)
+                    .map_err(|e| log::debug!("Skipping cooling device {}: {:#}", device, e))
+                    .ok()
+            })
+            .collect()
+    }
+
     pub fn get_max_temperature(&self) -> Result<f32> {
         let temps = self.get_all_temperatures()?;
         temps.into_iter()
@@ -145,28 +281,105 @@ impl ThermalManager {
             .ok_or_else(|| anyhow::anyhow!("No thermal zones found"))
     }
 
+    /// hwmon CPU sensor readings (package, per-core, Tctl/Tdie, ...)
+    pub fn get_hwmon_temperatures(&self) -> Vec<TemperatureReading> {
+        self.hwmon.read_all()
+    }
+
     pub fn get_cpu_temperature(&self) -> Result<f32> {
-        // Try to find CPU package temperature
-        for (zone_id, zone_path) in self.zones.iter().enumerate() {
-            let type_path = zone_path.join("type");
-            if let Ok(zone_type) = fs::read_to_string(&type_path) {
-                let zone_type = zone_type.trim().to_lowercase();
-                if zone_type.contains("x86_pkg_temp") || 
-                   zone_type.contains("cpu") ||
-                   zone_type.contains("core") {
-                    return self.get_temperature(zone_id);
+        match &self.cpu_temp_source {
+            CpuTempSource::Auto => {
+                if let Some(sensor) = self.hwmon.default_cpu_sensor() {
+                    return self.hwmon.read(sensor);
                 }
+                let zone = self.find_cpu_zone()
+                    .ok_or_else(|| anyhow::anyhow!("No CPU temperature sensor found"))?;
+                self.get_temperature(zone)
+            }
+            CpuTempSource::Hwmon { chip, label } => {
+                let sensor = self.hwmon.find(chip, label)
+                    .ok_or_else(|| anyhow::anyhow!("hwmon sensor {}/{} not found", chip, label))?;
+                self.hwmon.read(sensor)
+            }
+            CpuTempSource::Zone { type_name } => {
+                let zone = self.zones.keys().copied()
+                    .find(|&zone| self.get_zone_type(zone).is_ok_and(|t| &t == type_name))
+                    .ok_or_else(|| anyhow::anyhow!("Thermal zone '{}' not found", type_name))?;
+                self.get_temperature(zone)
             }
         }
+    }
+
+    /// hwmon chip the CPU temperature is read from, if it comes from hwmon
+    pub fn cpu_sensor_chip(&self) -> Option<&Path> {
+        let sensor = match &self.cpu_temp_source {
+            CpuTempSource::Auto => self.hwmon.default_cpu_sensor(),
+            CpuTempSource::Hwmon { chip, label } => self.hwmon.find(chip, label),
+            CpuTempSource::Zone { .. } => None,
+        };
+        sensor.map(|sensor| sensor.chip_path())
+    }
+
+    /// The thermal zone most likely to be the CPU package, preferring
+    /// x86_pkg_temp over zones that merely mention the CPU.
+    fn find_cpu_zone(&self) -> Option<usize> {
+        let types: Vec<(usize, String)> = self.zones.keys()
+            .map(|&zone| (zone, self.get_zone_type(zone).unwrap_or_default().to_lowercase()))
+            .collect();
+
+        types.iter().find(|(_, t)| t == "x86_pkg_temp")
+            .or_else(|| types.iter().find(|(_, t)| t.contains("cpu") || t.contains("core")))
+            .map(|(zone, _)| *zone)
+    }
+}
+
+impl FromStr for CpuTempSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s == "auto" {
+            return Ok(CpuTempSource::Auto);
+        }
+        if let Some(sensor) = s.strip_prefix("hwmon:") {
+            let (chip, label) = sensor.split_once('/')
+                .ok_or_else(|| anyhow::anyhow!("Invalid hwmon source '{}', expected hwmon:<chip>/<label>", s))?;
+            return Ok(CpuTempSource::Hwmon { chip: chip.to_string(), label: label.to_string() });
+        }
+        if let Some(type_name) = s.strip_prefix("zone:") {
+            return Ok(CpuTempSource::Zone { type_name: type_name.to_string() });
+        }
+        anyhow::bail!("Invalid CPU temperature source '{}', expected auto, hwmon:<chip>/<label> or zone:<type>", s)
+    }
+}
+
+impl TryFrom<String> for CpuTempSource {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for CpuTempSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CpuTempSource::Auto => write!(f, "auto"),
+            CpuTempSource::Hwmon { chip, label } => write!(f, "hwmon:{}/{}", chip, label),
+            CpuTempSource::Zone { type_name } => write!(f, "zone:{}", type_name),
+        }
+    }
+}
 
-        // Fallback to max temperature
-        self.get_max_temperature()
+impl From<CpuTempSource> for String {
+    fn from(source: CpuTempSource) -> Self {
+        source.to_string()
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::testing::TempDir;
 
     #[test]
     fn test_thermal_manager() {
@@ -176,4 +389,56 @@ mod tests {
             assert!(manager.get_zone_count() > 0);
         }
     }
+
+    #[test]
+    fn test_trip_points_and_cooling_bindings() {
+        let base = TempDir::new("thermal");
+        // Kernel ids with gaps, plus a zone and a device whose reads fail
+        let zone = base.join("thermal_zone2");
+        let cdev = base.join("cooling_device1");
+        fs::create_dir_all(&zone).unwrap();
+        fs::create_dir_all(&cdev).unwrap();
+        fs::create_dir_all(base.join("thermal_zone5")).unwrap();
+        fs::create_dir_all(base.join("cooling_device4")).unwrap();
+
+        fs::write(zone.join("type"), "x86_pkg_temp\n").unwrap();
+        fs::write(zone.join("temp"), "62000\n").unwrap();
+        fs::write(zone.join("policy"), "step_wise\n").unwrap();
+        fs::write(zone.join("trip_point_0_temp"), "96000\n").unwrap();
+        fs::write(zone.join("trip_point_0_type"), "passive\n").unwrap();
+        std::os::unix::fs::symlink("../cooling_device1", zone.join("cdev0")).unwrap();
+        fs::write(zone.join("cdev0_trip_point"), "0\n").unwrap();
+        fs::write(zone.join("cdev0_weight"), "0\n").unwrap();
+
+        fs::write(cdev.join("type"), "intel_powerclamp\n").unwrap();
+        fs::write(cdev.join("cur_state"), "25\n").unwrap();
+        fs::write(cdev.join("max_state"), "50\n").unwrap();
+
+        let manager = ThermalManager::from_base(&base).unwrap();
+        let info = manager.get_zone_info(2).unwrap();
+        assert_eq!(info.policy.as_deref(), Some("step_wise"));
+        assert_eq!(info.trip_points[0].trip_type, "passive");
+        assert_eq!(info.bindings.len(), 1);
+        assert_eq!(info.bindings[0].cooling_device, 1);
+        assert_eq!(info.bindings[0].trip_point, Some(0));
+
+        assert_eq!(manager.get_cooling_device_count(), 2);
+        let device = manager.get_cooling_device(1).unwrap();
+        assert_eq!((device.type_name.as_str(), device.cur_state, device.max_state), ("intel_powerclamp", 25, 50));
+
+        let zones = manager.get_all_zones();
+        assert_eq!(zones.iter().map(|z| z.id).collect::<Vec<_>>(), vec![2]);
+        let devices = manager.get_all_cooling_devices();
+        assert_eq!(devices.iter().map(|d| d.id).collect::<Vec<_>>(), vec![1]);
+        assert!(manager.get_zone_info(0).is_err());
+    }
+
+    #[test]
+    fn test_cpu_temp_source_parsing() {
+        let source: CpuTempSource = "hwmon:k10temp/Tdie".parse().unwrap();
+        assert_eq!(source, CpuTempSource::Hwmon { chip: "k10temp".to_string(), label: "Tdie".to_string() });
+        assert_eq!(source.to_string(), "hwmon:k10temp/Tdie");
+        assert_eq!("zone:x86_pkg_temp".parse::<CpuTempSource>().unwrap(), CpuTempSource::Zone { type_name: "x86_pkg_temp".to_string() });
+        assert!("wifi".parse::<CpuTempSource>().is_err());
+    }
 }
@@ -0,0 +1,257 @@
+// Thermal protection module
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::backend::CpuManager;
+use crate::config::ThermalConfig;
+
+/// Frequency limits in effect before the protector intervened
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedLimits {
+    /// By core; offline cores have no limit to save
+    pub max_freq_mhz: BTreeMap<usize, u32>,
+    pub turbo: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Intervention {
+    DisableTurbo,
+    LowerMaxFreq(u32),
+    RaiseMaxFreq(u32),
+    Emergency(u32),
+    Restore(SavedLimits),
+}
+
+enum ProtectionState {
+    Idle,
+    Active {
+        saved: SavedLimits,
+        cap_mhz: u32,
+        turbo_disabled: bool,
+        emergency: bool,
+    },
+}
+
+/// Enforces `ThermalConfig` temperature limits. Above `max_temp_celsius` it
+/// disables turbo and then lowers `scaling_max_freq` one step per update;
+/// once the temperature falls `hysteresis_celsius` below the limit it raises
+/// the cap again step by step and finally restores the original limits.
+/// At `emergency_temp_celsius` it drops straight to the hardware minimum.
+pub struct ThermalProtector {
+    config: ThermalConfig,
+    state: ProtectionState,
+}
+
+impl ThermalProtector {
+    pub fn new(config: ThermalConfig) -> Self {
+        Self {
+            config,
+            state: ProtectionState::Idle,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        matches!(self.state, ProtectionState::Active { .. })
+    }
+
+    /// Forget any intervention, e.g. after a profile has set new limits
+    pub fn reset(&mut self) {
+        self.state = ProtectionState::Idle;
+    }
+
+    pub fn update(&mut self, temp: f32, cpu_manager: &CpuManager) -> Result<Option<Intervention>> {
+        if !self.is_active() && temp >= self.config.max_temp_celsius {
+            self.state = ProtectionState::Active {
+                saved: SavedLimits {
+                    max_freq_mhz: cpu_manager
+                        .online_cores()
+                        .into_iter()
+                        .map(|core| Ok((core, cpu_manager.get_scaling_max_freq(core)?)))
+                        .collect::<Result<_>>()?,
+                    turbo: cpu_manager.is_turbo_enabled().unwrap_or(false),
+                },
+                cap_mhz: cpu_manager.get_scaling_max_freq(0)?,
+                turbo_disabled: false,
+                emergency: false,
+            };
+        }
+
+        let hw_min = cpu_manager.get_hardware_min_freq(0)?;
+        let Some(intervention) = self.decide(temp, hw_min) else {
+            return Ok(None);
+        };
+
+        Self::apply(&intervention, temp, cpu_manager)?;
+        Ok(Some(intervention))
+    }
+
+    fn decide(&mut self, temp: f32, hw_min: u32) -> Option<Intervention> {
+        let ProtectionState::Active { saved, cap_mhz, turbo_disabled, emergency } = &mut self.state else {
+            return None;
+        };
+        let step = self.config.throttle_step_mhz.max(1);
+
+        if temp >= self.config.emergency_temp_celsius {
+            if *emergency {
+                return None;
+            }
+            *emergency = true;
+            *turbo_disabled = true;
+            *cap_mhz = hw_min;
+            return Some(Intervention::Emergency(hw_min));
+        }
+        *emergency = false;
+
+        if temp >= self.config.max_temp_celsius {
+            if !*turbo_disabled {
+                *turbo_disabled = true;
+                if saved.turbo {
+                    return Some(Intervention::DisableTurbo);
+                }
+            }
+            if *cap_mhz <= hw_min {
+                return None;
+            }
+            *cap_mhz = cap_mhz.saturating_sub(step).max(hw_min);
+            return Some(Intervention::LowerMaxFreq(*cap_mhz));
+        }
+
+        if temp > self.config.max_temp_celsius - self.config.hysteresis_celsius {
+            return None;
+        }
+
+        let original_max = saved.max_freq_mhz.values().copied().max().unwrap_or(*cap_mhz);
+        if *cap_mhz + step < original_max {
+            *cap_mhz += step;
+            return Some(Intervention::RaiseMaxFreq(*cap_mhz));
+        }
+
+        let saved = saved.clone();
+        self.state = ProtectionState::Idle;
+        Some(Intervention::Restore(saved))
+    }
+
+    fn apply(intervention: &Intervention, temp: f32, cpu_manager: &CpuManager) -> Result<()> {
+        let set_max_all = |freq_mhz: u32| -> Result<()> {
+            for core in cpu_manager.online_cores() {
+                cpu_manager.set_scaling_max_freq(core, freq_mhz)?;
+            }
+            Ok(())
+        };
+
+        match intervention {
+            Intervention::DisableTurbo => {
+                log::warn!("Thermal protection: {:.1}°C, disabling turbo", temp);
+                cpu_manager.set_turbo(false)?;
+            }
+            Intervention::LowerMaxFreq(freq_mhz) => {
+                log::warn!("Thermal protection: {:.1}°C, lowering max frequency to {} MHz", temp, freq_mhz);
+                set_max_all(*freq_mhz)?;
+            }
+            Intervention::RaiseMaxFreq(freq_mhz) => {
+                log::info!("Thermal protection: {:.1}°C, raising max frequency to {} MHz", temp, freq_mhz);
+                set_max_all(*freq_mhz)?;
+            }
+            Intervention::Emergency(freq_mhz) => {
+                log::error!("Thermal emergency: {:.1}°C, dropping to hardware minimum {} MHz", temp, freq_mhz);
+                if let Err(e) = cpu_manager.set_turbo(false) {
+                    log::warn!("Failed to disable turbo: {}", e);
+                }
+                set_max_all(*freq_mhz)?;
+            }
+            Intervention::Restore(saved) => {
+                log::info!("Thermal protection: {:.1}°C, restoring original limits", temp);
+                // A core may have gone offline since its limit was saved
+                for (core, freq_mhz) in &saved.max_freq_mhz {
+                    if let Err(e) = cpu_manager.set_scaling_max_freq(*core, *freq_mhz) {
+                        log::warn!("Failed to restore max frequency of core {}: {}", core, e);
+                    }
+                }
+                if saved.turbo {
+                    cpu_manager.set_turbo(true)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Intervention {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Intervention::DisableTurbo => write!(f, "turbo disabled"),
+            Intervention::LowerMaxFreq(mhz) => write!(f, "max frequency lowered to {} MHz", mhz),
+            Intervention::RaiseMaxFreq(mhz) => write!(f, "max frequency raised to {} MHz", mhz),
+            Intervention::Emergency(mhz) => write!(f, "emergency limit of {} MHz", mhz),
+            Intervention::Restore(_) => write!(f, "original limits restored"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::{write_cpus, TempDir};
+    use std::fs;
+
+    fn active_protector() -> ThermalProtector {
+        let mut protector = ThermalProtector::new(ThermalConfig::default());
+        protector.state = ProtectionState::Active {
+            saved: SavedLimits { max_freq_mhz: BTreeMap::from([(0, 3000), (1, 3000)]), turbo: true },
+            cap_mhz: 3000,
+            turbo_disabled: false,
+            emergency: false,
+        };
+        protector
+    }
+
+    #[test]
+    fn test_step_throttling_and_restore() {
+        let mut protector = active_protector();
+
+        assert_eq!(protector.decide(91.0, 800), Some(Intervention::DisableTurbo));
+        assert_eq!(protector.decide(91.0, 800), Some(Intervention::LowerMaxFreq(2800)));
+        assert_eq!(protector.decide(90.5, 800), Some(Intervention::LowerMaxFreq(2600)));
+
+        // Inside the hysteresis band the cap is held
+        assert_eq!(protector.decide(87.0, 800), None);
+
+        assert_eq!(protector.decide(84.0, 800), Some(Intervention::RaiseMaxFreq(2800)));
+        assert!(matches!(protector.decide(84.0, 800), Some(Intervention::Restore(_))));
+        assert!(!protector.is_active());
+    }
+
+    #[test]
+    fn test_emergency_drops_to_minimum_once() {
+        let mut protector = active_protector();
+
+        assert_eq!(protector.decide(96.0, 800), Some(Intervention::Emergency(800)));
+        assert_eq!(protector.decide(97.0, 800), None);
+        assert_eq!(protector.decide(92.0, 800), None);
+    }
+
+    #[test]
+    fn test_offline_cores_are_skipped() {
+        if !nix::unistd::Uid::effective().is_root() {
+            eprintln!("writing limits needs root, skipping");
+            return;
+        }
+
+        let base = TempDir::new("protection");
+        write_cpus(&base, &[true, false, true]);
+        let cpu_manager = CpuManager::from_base(&base).unwrap();
+        let mut protector = ThermalProtector::new(ThermalConfig::default());
+
+        assert_eq!(protector.update(91.0, &cpu_manager).unwrap(), Some(Intervention::LowerMaxFreq(3800)));
+        let read_max = |core: usize| fs::read_to_string(base.join(format!("cpu{}/cpufreq/scaling_max_freq", core))).unwrap();
+        assert_eq!(read_max(0), "3800000");
+        assert_eq!(read_max(2), "3800000");
+
+        let Some(Intervention::Restore(saved)) = protector.update(50.0, &cpu_manager).unwrap() else {
+            panic!("expected the original limits to be restored");
+        };
+        assert_eq!(saved.max_freq_mhz, BTreeMap::from([(0, 4000), (2, 4000)]));
+        assert_eq!(read_max(2), "4000000");
+    }
+}
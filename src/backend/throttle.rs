@@ -0,0 +1,284 @@
+// Throttling detection module
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::backend::load::CpuLoadSampler;
+use crate::backend::{CpuManager, ThermalManager};
+
+const CPU_BASE: &str = "/sys/devices/system/cpu";
+
+/// A busy core running below this fraction of its base frequency (or its
+/// allowed max, if lower) is being limited. Turbo frequencies vary with the
+/// number of active cores, so only the base frequency is a reliable floor.
+const FREQUENCY_GAP_RATIO: f32 = 0.85;
+const BUSY_THRESHOLD_PERCENT: f32 = 80.0;
+
+/// Consecutive samples a frequency gap must persist before it is reported
+const SUSTAINED_SAMPLES: u32 = 5;
+
+const MAX_EVENTS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ThrottleCause {
+    /// The core crossed its thermal limit (PROCHOT)
+    CoreThermal { core: usize },
+    /// The package crossed its thermal limit
+    PackageThermal { package: usize },
+    /// A busy core is running well below its base frequency, e.g. from a power limit.
+    /// `max_mhz` is the base frequency, or the allowed max if that is lower.
+    FrequencyLimited { core: usize, actual_mhz: u32, max_mhz: u32 },
+    /// A kernel cooling device (intel_powerclamp, Processor, ...) engaged
+    CoolingDevice { device: usize, type_name: String, state: u64, max_state: u64 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThrottleEvent {
+    pub timestamp: DateTime<Local>,
+    pub cause: ThrottleCause,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ThrottleCounts {
+    pub core_thermal: u64,
+    pub package_thermal: u64,
+    pub frequency_limited: u64,
+    pub cooling_device: u64,
+}
+
+/// Watches the kernel's throttle counters, frequency headroom and cooling
+/// devices, recording an event each time something starts limiting the CPU.
+pub struct ThrottleMonitor {
+    base: PathBuf,
+    load: CpuLoadSampler,
+    core_counters: HashMap<usize, u64>,
+    package_counters: HashMap<usize, u64>,
+    gap_samples: HashMap<usize, u32>,
+    cooling_states: HashMap<usize, u64>,
+    events: VecDeque<ThrottleEvent>,
+    counts: ThrottleCounts,
+}
+
+impl ThrottleMonitor {
+    pub fn new() -> Self {
+        Self::from_base(Path::new(CPU_BASE))
+    }
+
+    fn from_base(base: &Path) -> Self {
+        Self {
+            base: base.to_path_buf(),
+            load: CpuLoadSampler::new(),
+            core_counters: HashMap::new(),
+            package_counters: HashMap::new(),
+            gap_samples: HashMap::new(),
+            cooling_states: HashMap::new(),
+            events: VecDeque::new(),
+            counts: ThrottleCounts::default(),
+        }
+    }
+
+    /// Kernel thermal throttle counters for a core: (core, package).
+    /// The package counter is repeated on every core of the package.
+    pub fn read_throttle_counters(&self, core: usize) -> Option<(u64, u64)> {
+        let dir = self.base.join(format!("cpu{}/thermal_throttle", core));
+        let read = |name: &str| -> Option<u64> {
+            fs::read_to_string(dir.join(name)).ok()?.trim().parse().ok()
+        };
+        Some((read("core_throttle_count")?, read("package_throttle_count")?))
+    }
+
+    /// Throttle counters summed since boot, with package counters counted once per package
+    pub fn kernel_totals(&self, cpu_manager: &CpuManager) -> ThrottleCounts {
+        let mut packages = HashMap::new();
+        let mut totals = ThrottleCounts::default();
+        for core in 0..cpu_manager.core_count() {
+            let Some((core_count, package_count)) = self.read_throttle_counters(core) else {
+                continue;
+            };
+            totals.core_thermal += core_count;
+            let package = cpu_manager.get_core_topology(core).map_or(0, |(package, _)| package);
+            packages.insert(package, package_count);
+        }
+        totals.package_thermal = packages.values().sum();
+        totals
+    }
+
+    /// Take a sample and return the throttle events it revealed
+    pub fn poll(&mut self, cpu_manager: &CpuManager, thermal_manager: &ThermalManager) -> Vec<ThrottleEvent> {
+        let mut causes = vec![];
+        self.check_counters(cpu_manager, &mut causes);
+        self.check_frequency_gaps(cpu_manager, &mut causes);
+        self.check_cooling_devices(thermal_manager, &mut causes);
+
+        let timestamp = Local::now();
+        causes
+            .into_iter()
+            .map(|cause| {
+                log::warn!("Throttling detected: {}", cause);
+                match cause {
+                    ThrottleCause::CoreThermal { .. } => self.counts.core_thermal += 1,
+                    ThrottleCause::PackageThermal { .. } => self.counts.package_thermal += 1,
+                    ThrottleCause::FrequencyLimited { .. } => self.counts.frequency_limited += 1,
+                    ThrottleCause::CoolingDevice { .. } => self.counts.cooling_device += 1,
+                }
+
+                let event = ThrottleEvent { timestamp, cause };
+                if self.events.len() == MAX_EVENTS {
+                    self.events.pop_front();
+                }
+                self.events.push_back(event.clone());
+                event
+            })
+            .collect()
+    }
+
+    /// Events seen since the monitor started, oldest first
+    pub fn events(&self) -> impl Iterator<Item = &ThrottleEvent> {
+        self.events.iter()
+    }
+
+    pub fn last_event(&self) -> Option<&ThrottleEvent> {
+        self.events.back()
+    }
+
+    pub fn counts(&self) -> ThrottleCounts {
+        self.counts
+    }
+
+    fn check_counters(&mut self, cpu_manager: &CpuManager, causes: &mut Vec<ThrottleCause>) {
+        for core in 0..cpu_manager.core_count() {
+            let Some((core_count, package_count)) = self.read_throttle_counters(core) else {
+                continue;
+            };
+            let package = cpu_manager.get_core_topology(core).map_or(0, |(package, _)| package);
+
+            if let Some(last) = self.core_counters.insert(core, core_count)
+                && core_count > last
+            {
+                causes.push(ThrottleCause::CoreThermal { core });
+            }
+            if let Some(last) = self.package_counters.insert(package, package_count)
+                && package_count > last
+            {
+                causes.push(ThrottleCause::PackageThermal { package });
+            }
+        }
+    }
+
+    fn check_frequency_gaps(&mut self, cpu_manager: &CpuManager, causes: &mut Vec<ThrottleCause>) {
+        let usage = match self.load.sample() {
+            Ok(usage) => usage,
+            Err(e) => {
+                log::debug!("Could not sample CPU load: {}", e);
+                return;
+            }
+        };
+
+        for (core, usage_percent) in usage {
+            let (Ok(actual_mhz), Some(max_mhz)) = (cpu_manager.get_frequency(core), expected_freq(cpu_manager, core)) else {
+                continue;
+            };
+
+            let samples = self.gap_samples.entry(core).or_insert(0);
+            if !is_frequency_limited(usage_percent, actual_mhz, max_mhz) {
+                *samples = 0;
+                continue;
+            }
+
+            *samples += 1;
+            if *samples == SUSTAINED_SAMPLES {
+                causes.push(ThrottleCause::FrequencyLimited { core, actual_mhz, max_mhz });
+            }
+        }
+    }
+
+    fn check_cooling_devices(&mut self, thermal_manager: &ThermalManager, causes: &mut Vec<ThrottleCause>) {
+        for device in thermal_manager.get_all_cooling_devices() {
+            let last = self.cooling_states.insert(device.id, device.cur_state).unwrap_or(0);
+            if device.cur_state > last {
+                causes.push(ThrottleCause::CoolingDevice {
+                    device: device.id,
+                    type_name: device.type_name,
+                    state: device.cur_state,
+                    max_state: device.max_state,
+                });
+            }
+        }
+    }
+}
+
+impl Default for ThrottleMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Frequency a busy core should reach at least. Without a base frequency
+/// there is no telling turbo headroom from a limit, so nothing is checked.
+fn expected_freq(cpu_manager: &CpuManager, core: usize) -> Option<u32> {
+    let base = cpu_manager.get_base_freq(core).ok()?;
+    let allowed = cpu_manager.get_scaling_max_freq(core).ok()?;
+    Some(base.min(allowed))
+}
+
+fn is_frequency_limited(usage_percent: f32, actual_mhz: u32, max_mhz: u32) -> bool {
+    usage_percent >= BUSY_THRESHOLD_PERCENT && (actual_mhz as f32) < max_mhz as f32 * FREQUENCY_GAP_RATIO
+}
+
+impl fmt::Display for ThrottleCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThrottleCause::CoreThermal { core } => write!(f, "core {} thermal limit", core),
+            ThrottleCause::PackageThermal { package } => write!(f, "package {} thermal limit", package),
+            ThrottleCause::FrequencyLimited { core, actual_mhz, max_mhz } => {
+                write!(f, "core {} busy at {} MHz, below {} MHz", core, actual_mhz, max_mhz)
+            }
+            ThrottleCause::CoolingDevice { device, type_name, state, max_state } => {
+                write!(f, "cooling device {} ({}) at state {}/{}", device, type_name, state, max_state)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::{write_cpus, TempDir};
+
+    #[test]
+    fn test_throttle_counters_and_frequency_gap() {
+        let base = TempDir::new("throttle");
+        let dir = base.join("cpu0/thermal_throttle");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("core_throttle_count"), "12\n").unwrap();
+        fs::write(dir.join("package_throttle_count"), "3\n").unwrap();
+
+        let monitor = ThrottleMonitor::from_base(&base);
+        assert_eq!(monitor.read_throttle_counters(0), Some((12, 3)));
+        assert_eq!(monitor.read_throttle_counters(1), None);
+
+        assert!(is_frequency_limited(95.0, 2000, 4000));
+        assert!(!is_frequency_limited(95.0, 3900, 4000));
+        assert!(!is_frequency_limited(10.0, 800, 4000));
+    }
+
+    #[test]
+    fn test_all_core_turbo_is_not_throttling() {
+        let base = TempDir::new("throttle-base");
+        write_cpus(&base, &[true, true]);
+        fs::write(base.join("cpu0/cpufreq/base_frequency"), "2600000\n").unwrap();
+        let cpu_manager = CpuManager::from_base(&base).unwrap();
+
+        // All-core turbo at 3.2 GHz is well below the 4 GHz single-core max
+        let expected = expected_freq(&cpu_manager, 0).unwrap();
+        assert_eq!(expected, 2600);
+        assert!(!is_frequency_limited(100.0, 3200, expected));
+        assert!(is_frequency_limited(100.0, 1800, expected));
+
+        // Without a base frequency there is nothing reliable to compare against
+        assert_eq!(expected_freq(&cpu_manager, 1), None);
+    }
+}
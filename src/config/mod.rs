@@ -1,8 +1,11 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use crate::backend::profile::Profile;
+use crate::backend::fan::FanCurve;
+use crate::backend::thermal::CpuTempSource;
+use crate::utils::temperature::{deserialize_celsius, TemperatureUnit};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -16,6 +19,13 @@ pub struct Config {
     pub monitoring: MonitoringConfig,
     #[serde(default)]
     pub logging: LoggingConfig,
+    #[serde(default)]
+    pub battery: BatteryConfig,
+    #[serde(default)]
+    pub service: ServiceConfig,
+    /// User-defined profiles, looked up by name before the built-in ones
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,8 +36,9 @@ pub struct GeneralConfig {
     pub start_minimized: bool,
     #[serde(default = "default_polling_interval")]
     pub polling_interval_ms: u64,
-    #[serde(default = "default_temp_unit")]
-    pub temperature_unit: String,
+    /// Display unit: `celsius`, `fahrenheit` or `kelvin`
+    #[serde(default)]
+    pub temperature_unit: TemperatureUnit,
     #[serde(default = "default_notification_level")]
     pub notification_level: String,
 }
@@ -40,9 +51,9 @@ pub struct AutoTuneConfig {
     pub ac_profile: String,
     #[serde(default = "default_battery_profile")]
     pub battery_profile: String,
-    #[serde(default = "default_temp_high")]
+    #[serde(default = "default_temp_high", deserialize_with = "deserialize_celsius")]
     pub temp_threshold_high: f32,
-    #[serde(default = "default_temp_low")]
+    #[serde(default = "default_temp_low", deserialize_with = "deserialize_celsius")]
     pub temp_threshold_low: f32,
     #[serde(default = "default_load_high")]
     pub load_threshold_high: f32,
@@ -52,12 +63,46 @@ pub struct AutoTuneConfig {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ThermalConfig {
-    #[serde(default = "default_max_temp")]
+    /// Thresholds are in Celsius unless written with a unit, e.g. `"194F"`
+    #[serde(default = "default_max_temp", deserialize_with = "deserialize_celsius")]
     pub max_temp_celsius: f32,
-    #[serde(default = "default_emergency_temp")]
+    #[serde(default = "default_emergency_temp", deserialize_with = "deserialize_celsius")]
     pub emergency_temp_celsius: f32,
+    /// How far thermal protection lowers the max frequency per polling interval
+    #[serde(default = "default_throttle_step")]
+    pub throttle_step_mhz: u32,
+    /// How far below `max_temp_celsius` the CPU must cool before limits are raised again
+    #[serde(default = "default_hysteresis")]
+    pub hysteresis_celsius: f32,
     #[serde(default)]
     pub fan_control_enabled: bool,
+    /// How far the temperature must fall before fans slow down again
+    #[serde(default = "default_fan_hysteresis")]
+    pub fan_hysteresis_celsius: f32,
+    /// Fans to drive, as `<chip>/pwm<N>` (e.g. `nct6775/pwm2`). Empty means
+    /// the fans on the CPU temperature sensor's chip.
+    #[serde(default)]
+    pub fans: Vec<String>,
+    /// `auto`, `hwmon:<chip>/<label>` (e.g. `hwmon:k10temp/Tdie`) or `zone:<type>`
+    #[serde(default)]
+    pub cpu_temp_source: CpuTempSource,
+    /// Fan curve used when the active profile does not define one
+    #[serde(default)]
+    pub fan_curve: FanCurve,
+    /// Gains for profiles that hold a `target_temp_celsius`
+    #[serde(default)]
+    pub pid: PidConfig,
+}
+
+/// PID gains, in MHz per °C of error (`kp`), per °C·s (`ki`) and per °C/s (`kd`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PidConfig {
+    #[serde(default = "default_pid_kp")]
+    pub kp: f32,
+    #[serde(default = "default_pid_ki")]
+    pub ki: f32,
+    #[serde(default = "default_pid_kd")]
+    pub kd: f32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -82,10 +127,74 @@ pub struct LoggingConfig {
     pub max_log_size_mb: u64,
 }
 
+/// Desired charge thresholds, reapplied by the service at startup and after resume
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatteryConfig {
+    #[serde(default)]
+    pub charge_start_threshold: Option<u8>,
+    #[serde(default)]
+    pub charge_end_threshold: Option<u8>,
+}
+
+/// Settings for the background service (`cpu-power-manager service`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceConfig {
+    /// Own `com.cpupowermanager.Daemon` on the system bus so unprivileged clients can make changes
+    #[serde(default = "default_true")]
+    pub dbus_enabled: bool,
+    /// Line-delimited JSON control socket for hosts without a system bus
+    #[serde(default)]
+    pub socket: SocketConfig,
+    /// Stand in for power-profiles-daemon so desktop power mode switches apply our profiles
+    #[serde(default)]
+    pub power_profiles: PowerProfilesConfig,
+    /// Prometheus metrics over HTTP and/or a node_exporter textfile
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+}
+
+/// Who may connect to the control socket, checked with `SO_PEERCRED` and `SO_PEERGROUPS`.
+/// Root is always allowed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SocketConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_socket_path")]
+    pub path: String,
+    #[serde(default)]
+    pub allowed_uids: Vec<u32>,
+    #[serde(default)]
+    pub allowed_gids: Vec<u32>,
+}
+
+/// Where the service exports metrics; both are off unless set
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    /// Serve `/metrics` on 127.0.0.1 at this port
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Rewrite this `.prom` file every polling interval for node_exporter's textfile collector
+    #[serde(default)]
+    pub textfile: Option<String>,
+}
+
+/// Which profile each desktop power mode applies
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerProfilesConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_power_saver_profile")]
+    pub power_saver: String,
+    #[serde(default = "default_balanced_profile")]
+    pub balanced: String,
+    #[serde(default = "default_performance_profile")]
+    pub performance: String,
+}
+
 // Default value functions
 fn default_true() -> bool { true }
 fn default_polling_interval() -> u64 { 1000 }
-fn default_temp_unit() -> String { "celsius".to_string() }
+fn default_socket_path() -> String { "/run/cpu-power-manager/control.sock".to_string() }
 fn default_notification_level() -> String { "important".to_string() }
 fn default_ac_profile() -> String { "performance".to_string() }
 fn default_battery_profile() -> String { "balanced".to_string() }
@@ -95,6 +204,15 @@ fn default_load_high() -> f32 { 70.0 }
 fn default_load_low() -> f32 { 30.0 }
 fn default_max_temp() -> f32 { 90.0 }
 fn default_emergency_temp() -> f32 { 95.0 }
+fn default_throttle_step() -> u32 { 200 }
+fn default_hysteresis() -> f32 { 5.0 }
+fn default_fan_hysteresis() -> f32 { 3.0 }
+fn default_pid_kp() -> f32 { 100.0 }
+fn default_pid_ki() -> f32 { 10.0 }
+fn default_pid_kd() -> f32 { 20.0 }
+fn default_power_saver_profile() -> String { "powersave".to_string() }
+fn default_balanced_profile() -> String { "balanced".to_string() }
+fn default_performance_profile() -> String { "performance".to_string() }
 fn default_graph_history() -> u64 { 300 }
 fn default_log_level() -> String { "info".to_string() }
 fn default_log_path() -> String {
@@ -109,7 +227,7 @@ impl Default for GeneralConfig {
             auto_start: true,
             start_minimized: false,
             polling_interval_ms: 1000,
-            temperature_unit: "celsius".to_string(),
+            temperature_unit: TemperatureUnit::Celsius,
             notification_level: "important".to_string(),
         }
     }
@@ -134,7 +252,24 @@ impl Default for ThermalConfig {
         Self {
             max_temp_celsius: 90.0,
             emergency_temp_celsius: 95.0,
+            throttle_step_mhz: 200,
+            hysteresis_celsius: 5.0,
             fan_control_enabled: false,
+            fan_hysteresis_celsius: 3.0,
+            fans: vec![],
+            cpu_temp_source: CpuTempSource::Auto,
+            fan_curve: FanCurve::default(),
+            pid: PidConfig::default(),
+        }
+    }
+}
+
+impl Default for PidConfig {
+    fn default() -> Self {
+        Self {
+            kp: 100.0,
+            ki: 10.0,
+            kd: 20.0,
         }
     }
 }
@@ -160,6 +295,39 @@ impl Default for LoggingConfig {
     }
 }
 
+impl Default for ServiceConfig {
+    fn default() -> Self {
+        Self {
+            dbus_enabled: true,
+            socket: SocketConfig::default(),
+            power_profiles: PowerProfilesConfig::default(),
+            metrics: MetricsConfig::default(),
+        }
+    }
+}
+
+impl Default for SocketConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            path: default_socket_path(),
+            allowed_uids: vec![],
+            allowed_gids: vec![],
+        }
+    }
+}
+
+impl Default for PowerProfilesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            power_saver: "powersave".to_string(),
+            balanced: "balanced".to_string(),
+            performance: "performance".to_string(),
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -168,10 +336,16 @@ impl Default for Config {
             thermal: ThermalConfig::default(),
             monitoring: MonitoringConfig::default(),
             logging: LoggingConfig::default(),
+            battery: BatteryConfig::default(),
+            service: ServiceConfig::default(),
+            profiles: Vec::new(),
         }
     }
 }
 
+/// Read by the background service, which runs on a read-only file system
+pub const SYSTEM_CONFIG_PATH: &str = "/etc/cpu-power-manager/config.toml";
+
 pub struct ConfigManager {
     config: Config,
     config_path: PathBuf,
@@ -188,6 +362,26 @@ impl ConfigManager {
         })
     }
 
+    /// The service's configuration, shared by all users. A missing file means
+    /// defaults; nothing is written.
+    pub fn system() -> Result<Self> {
+        Self::read_only(Path::new(SYSTEM_CONFIG_PATH))
+    }
+
+    fn read_only(path: &Path) -> Result<Self> {
+        let config = if path.exists() {
+            Self::load_config(path)?
+        } else {
+            log::debug!("{} not found, using defaults", path.display());
+            Config::default()
+        };
+
+        Ok(Self {
+            config,
+            config_path: path.to_path_buf(),
+        })
+    }
+
     fn get_config_path() -> Result<PathBuf> {
         let config_dir = if let Ok(xdg_config) = std::env::var("XDG_CONFIG_HOME") {
             PathBuf::from(xdg_config)
@@ -204,7 +398,7 @@ impl ConfigManager {
         Ok(app_config_dir.join("config.toml"))
     }
 
-    fn load_config(path: &PathBuf) -> Result<Config> {
+    fn load_config(path: &Path) -> Result<Config> {
         if path.exists() {
             let config_str = fs::read_to_string(path)
                 .context("Failed to read config file")?;
@@ -233,6 +427,10 @@ impl ConfigManager {
     }
 
     pub fn get_profile(&self, name: &str) -> Result<Profile> {
+        if let Some(profile) = self.config.profiles.iter().find(|p| p.name.eq_ignore_ascii_case(name)) {
+            return Ok(profile.clone());
+        }
+
         match name {
             "performance" => Ok(Profile::performance()),
             "balanced" => Ok(Profile::balanced()),
@@ -241,4 +439,37 @@ impl ConfigManager {
             _ => anyhow::bail!("Profile '{}' not found", name),
         }
     }
+
+    /// Built-in profile names followed by custom ones that do not shadow them
+    pub fn profile_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = ["performance", "balanced", "powersave", "silent"]
+            .iter()
+            .map(|name| name.to_string())
+            .collect();
+        for profile in &self.config.profiles {
+            if !names.iter().any(|name| name.eq_ignore_ascii_case(&profile.name)) {
+                names.push(profile.name.clone());
+            }
+        }
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::TempDir;
+
+    #[test]
+    fn test_read_only_config() {
+        let dir = TempDir::new("config");
+        let path = dir.join("config.toml");
+
+        let manager = ConfigManager::read_only(&path).unwrap();
+        assert!(manager.get_config().service.dbus_enabled);
+        assert!(!path.exists());
+
+        fs::write(&path, "[service]\ndbus_enabled = false\n").unwrap();
+        assert!(!ConfigManager::read_only(&path).unwrap().get_config().service.dbus_enabled);
+    }
 }
@@ -6,7 +6,7 @@ mod ui;
 mod utils;
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use env_logger::Env;
 use gtk4::prelude::*;
 use gtk4::{Application};
@@ -29,18 +29,91 @@ struct Cli {
     minimized: bool,
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+enum StatusFormat {
+    Json,
+    Toml,
+    Table,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum MonitorFormat {
+    Text,
+    Csv,
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Show current CPU status
-    Status,
+    Status {
+        /// Print structured output instead of the summary
+        #[arg(long, value_enum)]
+        format: Option<StatusFormat>,
+    },
     /// Set CPU governor
     SetGovernor { governor: String },
     /// Set CPU frequency (in MHz)
     SetFrequency { frequency: u32 },
     /// Enable/disable turbo boost
     SetTurbo { enabled: bool },
+    /// Set minimum and maximum scaling frequency on all cores (in MHz)
+    SetLimits {
+        #[arg(long)]
+        min: u32,
+        #[arg(long)]
+        max: u32,
+    },
+    /// Bring a CPU core online or take it offline
+    SetCore { core: usize, online: bool },
     /// Apply a profile
     ApplyProfile { name: String },
+    /// List available profiles
+    Profiles,
+    /// Continuously show frequency, usage, temperature, package power, governor and turbo
+    Monitor {
+        /// Refresh interval in milliseconds (default: general.polling_interval_ms)
+        #[arg(long)]
+        interval: Option<u64>,
+        /// Include per-core readings
+        #[arg(long)]
+        cores: bool,
+        /// Print a single sample and exit
+        #[arg(long)]
+        once: bool,
+        #[arg(long, value_enum, default_value = "text")]
+        format: MonitorFormat,
+    },
+    /// Record metrics to a .csv or .jsonl file
+    Record {
+        #[arg(long, short)]
+        output: std::path::PathBuf,
+        /// How long to record, e.g. 90s, 10m or 1h (default: until interrupted)
+        #[arg(long, value_parser = system::recording::parse_duration)]
+        duration: Option<std::time::Duration>,
+        /// Sampling interval in milliseconds (default: general.polling_interval_ms)
+        #[arg(long)]
+        interval: Option<u64>,
+    },
+    /// Summarize a recording made with `record`
+    Report {
+        input: std::path::PathBuf,
+        /// Report time spent above this temperature, e.g. 80 or 176F (default: thermal.max_temp_celsius)
+        #[arg(long, value_parser = utils::temperature::parse_temperature)]
+        temp_threshold: Option<f32>,
+    },
+    /// Show thermal zones, trip points and cooling devices
+    Thermal,
+    /// Show or set battery charge thresholds (in percent)
+    ChargeThresholds {
+        /// Start charging below this level
+        #[arg(long)]
+        start: Option<u8>,
+        /// Stop charging at this level
+        #[arg(long)]
+        end: Option<u8>,
+    },
+    /// Print service events (profile, power source and thermal changes) as JSON lines
+    Events,
     /// Start the background service
     Service,
     /// Show version information
@@ -56,6 +129,9 @@ fn main() -> Result<()> {
 
     log::info!("Starting CPU Power Manager v{}", env!("CARGO_PKG_VERSION"));
 
+    // Never leave fans stuck at a manual speed if we crash
+    backend::fan::install_panic_hook();
+
     // Handle CLI commands
     if let Some(command) = cli.command {
         return handle_cli_command(command);
@@ -89,7 +165,8 @@ fn handle_cli_command(command: Commands) -> Result<()> {
     let cpu_manager = CpuManager::new()?;
 
     match command {
-        Commands::Status => {
+        Commands::Status { format: Some(format) } => print_status(&cpu_manager, format)?,
+        Commands::Status { format: None } => {
             println!("CPU Status:");
             let info = cpu_manager.get_cpu_info()?;
             println!("  Model: {}", info.model);
@@ -100,9 +177,61 @@ fn handle_cli_command(command: Commands) -> Result<()> {
                 println!("    Core {}: {} MHz", core, freq);
             }
             println!("  Turbo: {}", if cpu_manager.is_turbo_enabled()? { "Enabled" } else { "Disabled" });
+            let config_manager = config::ConfigManager::new()?;
+            let unit = config_manager.get_config().general.temperature_unit;
+            let mut thermal_manager = backend::ThermalManager::new()?;
+            thermal_manager.set_cpu_temp_source(config_manager.get_config().thermal.cpu_temp_source.clone());
+            match thermal_manager.get_cpu_temperature() {
+                Ok(temp) => println!("  CPU Temperature: {}", unit.format(temp, 1)),
+                Err(e) => println!("  CPU Temperature: unavailable ({})", e),
+            }
+            let throttle = backend::ThrottleMonitor::new().kernel_totals(&cpu_manager);
+            println!("  Thermal Throttling: {} core / {} package events since boot", throttle.core_thermal, throttle.package_thermal);
+            for device in thermal_manager.get_all_cooling_devices() {
+                if device.cur_state > 0 {
+                    println!("    Cooling device {} ({}) active: state {}/{}", device.id, device.type_name, device.cur_state, device.max_state);
+                }
+            }
+            for fan in backend::FanManager::new()?.get_all_fans() {
+                match fan.rpm {
+                    Some(rpm) => println!("  Fan {}/pwm{}: {} RPM", fan.chip, fan.index, rpm),
+                    None => println!("  Fan {}/pwm{}: PWM {}", fan.chip, fan.index, fan.pwm.map_or("--".to_string(), |p| p.to_string())),
+                }
+            }
+            let platform_profile = backend::PlatformProfileManager::new()?;
+            if platform_profile.is_supported() {
+                println!("  Platform Profile: {}", platform_profile.get_profile()?);
+            }
+            match sample_power() {
+                Ok(zones) => {
+                    if let Some(watts) = backend::EnergySampler::package_watts(&zones) {
+                        println!("  Package Power: {:.1} W", watts);
+                    }
+                    for zone in zones {
+                        println!("    {}: {:.1} W", zone.name, zone.watts);
+                    }
+                }
+                Err(e) => println!("  Package Power: unavailable ({})", e),
+            }
+
+            let power_manager = backend::PowerManager::new()?;
+            let batteries = power_manager.get_batteries().unwrap_or_default();
+            for battery in &batteries {
+                match battery.capacity_percent {
+                    Some(capacity) => println!("  Battery {}: {}% ({})", battery.name, capacity, battery.status),
+                    None => println!("  Battery {}: {}", battery.name, battery.status),
+                }
+            }
+            let estimate = backend::power::BatteryEstimator::new().update(&batteries);
+            if let Some(watts) = estimate.discharge_watts {
+                println!("  Discharge Rate: {:.1} W", watts);
+            }
+            if let Some(remaining) = estimate.format_time_remaining() {
+                println!("  Time Remaining: {}", remaining);
+            }
         }
         Commands::SetGovernor { governor } => {
-            cpu_manager.set_governor_all(&governor)?;
+            system::controller::connect()?.set_governor(&governor)?;
             println!("Governor set to: {}", governor);
         }
         Commands::SetFrequency { frequency } => {
@@ -110,19 +239,106 @@ fn handle_cli_command(command: Commands) -> Result<()> {
             println!("Frequency set to: {} MHz", frequency);
         }
         Commands::SetTurbo { enabled } => {
-            cpu_manager.set_turbo(enabled)?;
+            system::controller::connect()?.set_turbo(enabled)?;
             println!("Turbo boost: {}", if enabled { "Enabled" } else { "Disabled" });
         }
+        Commands::SetLimits { min, max } => {
+            system::controller::connect()?.set_frequency_limits(min, max)?;
+            println!("Frequency limits set to: {} - {} MHz", min, max);
+        }
+        Commands::SetCore { core, online } => {
+            system::controller::connect()?.set_core_online(core, online)?;
+            println!("Core {}: {}", core, if online { "Online" } else { "Offline" });
+        }
         Commands::ApplyProfile { name } => {
-            let config_manager = config::ConfigManager::new()?;
-            let profile = config_manager.get_profile(&name)?;
-            profile.apply(&cpu_manager)?;
+            system::controller::connect()?.apply_profile(&name)?;
             println!("Profile '{}' applied", name);
         }
+        Commands::Profiles => {
+            for name in system::controller::connect()?.list_profiles()? {
+                println!("{}", name);
+            }
+        }
+        Commands::Monitor { interval, cores, once, format } => run_monitor(interval, cores, once, format)?,
+        Commands::Record { output, duration, interval } => {
+            let config_manager = config::ConfigManager::new()?;
+            let interval = std::time::Duration::from_millis(
+                interval.unwrap_or(config_manager.get_config().general.polling_interval_ms).max(100),
+            );
+            let mut monitor = system::monitor::Monitor::new(&config_manager)?;
+            let mut recorder = system::recording::Recorder::create(&output)?;
+            let started = std::time::Instant::now();
+
+            println!("Recording to {} (Ctrl+C to stop)", output.display());
+            let mut count = 0;
+            while duration.is_none_or(|duration| started.elapsed() < duration) {
+                std::thread::sleep(interval);
+                recorder.write(&monitor.sample()?)?;
+                count += 1;
+            }
+            println!("Recorded {} samples", count);
+        }
+        Commands::Report { input, temp_threshold } => {
+            let config_manager = config::ConfigManager::new()?;
+            let samples = system::recording::load(&input)?;
+            let threshold = temp_threshold.unwrap_or(config_manager.get_config().thermal.max_temp_celsius);
+            let unit = config_manager.get_config().general.temperature_unit;
+            print!("{}", system::recording::Report::from_samples(&samples, threshold, unit));
+        }
+        Commands::Thermal => {
+            let unit = config::ConfigManager::new()?.get_config().general.temperature_unit;
+            let thermal_manager = backend::ThermalManager::new()?;
+            let devices = thermal_manager.get_all_cooling_devices();
+
+            println!("Thermal Zones:");
+            for zone in thermal_manager.get_all_zones() {
+                println!("  Zone {}: {} {} (policy: {})",
+                    zone.id, zone.type_name, unit.format(zone.temp_celsius, 1), zone.policy.as_deref().unwrap_or("--"));
+                for trip in &zone.trip_points {
+                    println!("    Trip {}: {} {}", trip.id, unit.format(trip.temp_celsius, 1), trip.trip_type);
+                }
+                for binding in &zone.bindings {
+                    let name = devices.iter()
+                        .find(|d| d.id == binding.cooling_device)
+                        .map_or("unknown", |d| d.type_name.as_str());
+                    match binding.trip_point {
+                        Some(trip) => println!("    Cooling: device {} ({}) on trip {}", binding.cooling_device, name, trip),
+                        None => println!("    Cooling: device {} ({})", binding.cooling_device, name),
+                    }
+                }
+            }
+
+            println!("Cooling Devices:");
+            for device in devices {
+                let throttling = if device.cur_state > 0 { " [active]" } else { "" };
+                println!("  Device {}: {} state {}/{}{}", device.id, device.type_name, device.cur_state, device.max_state, throttling);
+            }
+        }
+        Commands::ChargeThresholds { start, end } => {
+            let power_manager = backend::PowerManager::new()?;
+            if start.is_some() || end.is_some() {
+                let thresholds = backend::power::ChargeThresholds { start, end };
+                power_manager.set_charge_thresholds_all(thresholds)?;
+            }
+
+            let batteries = power_manager.get_threshold_batteries()?;
+            if batteries.is_empty() {
+                println!("Charge thresholds are not supported on this system");
+            }
+            for battery in batteries {
+                let thresholds = power_manager.get_charge_thresholds(&battery);
+                let format = |v: Option<u8>| v.map(|v| format!("{}%", v)).unwrap_or_else(|| "n/a".to_string());
+                println!("{}: start {}, end {}", battery, format(thresholds.start), format(thresholds.end));
+            }
+        }
+        Commands::Events => {
+            for event in system::controller::subscribe()? {
+                println!("{}", serde_json::to_string(&event)?);
+            }
+        }
         Commands::Service => {
             log::info!("Starting background service");
-            // TODO: Implement service mode with auto-tuning
-            println!("Service mode not yet implemented");
+            system::Service::new()?.run()?;
         }
         Commands::Version => {
             println!("CPU Power Manager v{}", env!("CARGO_PKG_VERSION"));
@@ -133,6 +349,144 @@ fn handle_cli_command(command: Commands) -> Result<()> {
     Ok(())
 }
 
+/// Full status as JSON, TOML or aligned tables; temperatures in the table use the configured unit
+fn print_status(cpu_manager: &backend::CpuManager, format: StatusFormat) -> Result<()> {
+    let config_manager = config::ConfigManager::new()?;
+    let mut thermal_manager = backend::ThermalManager::new()?;
+    thermal_manager.set_cpu_temp_source(config_manager.get_config().thermal.cpu_temp_source.clone());
+    let status = system::status::SystemStatus::collect(cpu_manager, &thermal_manager, &backend::PowerManager::new()?)?;
+
+    match format {
+        StatusFormat::Json => println!("{}", serde_json::to_string_pretty(&status)?),
+        StatusFormat::Toml => print!("{}", toml::to_string(&status)?),
+        StatusFormat::Table => print_status_table(&status, config_manager.get_config().general.temperature_unit),
+    }
+    Ok(())
+}
+
+fn print_status_table(status: &system::status::SystemStatus, unit: utils::temperature::TemperatureUnit) {
+    let or_na = |value: Option<String>| value.unwrap_or_else(|| "n/a".to_string());
+    let temp = |celsius: Option<f32>| or_na(celsius.map(|t| unit.format(t, 1)));
+
+    println!("{:<14} {}", "Model", status.cpu.model);
+    println!("{:<14} {}", "Vendor", status.cpu.vendor);
+    println!("{:<14} {:?}", "Driver", status.cpu.driver);
+    println!("{:<14} {} - {} MHz", "Hardware Range", status.cpu.min_freq, status.cpu.max_freq);
+    println!("{:<14} {}", "Turbo", or_na(status.turbo.map(|on| if on { "enabled" } else { "disabled" }.to_string())));
+    println!("{:<14} {}", "EPP", or_na(status.epp.clone()));
+    println!("{:<14} {}", "CPU Temp", temp(status.cpu_temp_celsius));
+    println!("{:<14} {}", "Power Source", or_na(status.on_ac_power.map(|ac| if ac { "AC" } else { "battery" }.to_string())));
+
+    println!();
+    println!("{:>4}  {:<6}  {:<12}  {:>6}  {:>6}  {:>6}  {:>6}  {:>9}", "CORE", "ONLINE", "GOVERNOR", "MHZ", "MIN", "MAX", "USAGE", "TEMP");
+    for core in &status.cores {
+        if !core.online {
+            println!("{:>4}  {:<6}", core.core_id, "no");
+            continue;
+        }
+        println!(
+            "{:>4}  {:<6}  {:<12}  {:>6}  {:>6}  {:>6}  {:>5.1}%  {:>9}",
+            core.core_id, if core.online { "yes" } else { "no" }, core.governor, core.current_freq,
+            core.min_freq, core.max_freq, core.usage_percent, temp(core.temperature_celsius)
+        );
+    }
+
+    if !status.thermal_zones.is_empty() {
+        println!();
+        println!("{:>4}  {:<20}  {:>9}  POLICY", "ZONE", "TYPE", "TEMP");
+        for zone in &status.thermal_zones {
+            println!("{:>4}  {:<20}  {:>9}  {}", zone.id, zone.type_name, temp(Some(zone.temp_celsius)), or_na(zone.policy.clone()));
+        }
+    }
+
+    if !status.batteries.is_empty() {
+        println!();
+        println!("{:<8}  {:<12}  {:>8}  {:>7}", "BATTERY", "STATUS", "CAPACITY", "POWER");
+        for battery in &status.batteries {
+            println!(
+                "{:<8}  {:<12}  {:>8}  {:>7}",
+                battery.name, battery.status,
+                or_na(battery.capacity_percent.map(|c| format!("{}%", c))),
+                or_na(battery.power_w.map(|w| format!("{:.1} W", w)))
+            );
+        }
+    }
+}
+
+/// Sample every interval until interrupted. Text output redraws the screen;
+/// CSV prints the header once and then one row per sample.
+fn run_monitor(interval: Option<u64>, cores: bool, once: bool, format: MonitorFormat) -> Result<()> {
+    let config_manager = config::ConfigManager::new()?;
+    let interval = std::time::Duration::from_millis(
+        interval.unwrap_or(config_manager.get_config().general.polling_interval_ms).max(100),
+    );
+    let unit = config_manager.get_config().general.temperature_unit;
+    let mut monitor = system::monitor::Monitor::new(&config_manager)?;
+
+    for count in 0.. {
+        std::thread::sleep(interval);
+        let sample = monitor.sample()?;
+
+        match format {
+            MonitorFormat::Csv => {
+                if count == 0 {
+                    println!("{}", sample.csv_header(cores));
+                }
+                println!("{}", sample.csv_row(cores));
+            }
+            MonitorFormat::Text => {
+                if !once {
+                    // Clear the screen and move the cursor home
+                    print!("\x1b[2J\x1b[H");
+                }
+                print_monitor_sample(&sample, cores, unit);
+            }
+        }
+
+        if once {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn print_monitor_sample(sample: &system::monitor::Sample, cores: bool, unit: utils::temperature::TemperatureUnit) {
+    let temp = |celsius: Option<f32>| celsius.map_or("--".to_string(), |t| unit.format(t, 1));
+    let percent = |value: Option<f32>| value.map_or("--".to_string(), |v| format!("{:.1}%", v));
+
+    println!("CPU Monitor ({})", sample.timestamp.format("%H:%M:%S"));
+    if let Some(profile) = &sample.profile {
+        println!("  Profile: {}", profile);
+    }
+    println!("  Governor: {}", sample.governor);
+    println!("  Turbo: {}", sample.turbo.map_or("--", |on| if on { "Enabled" } else { "Disabled" }));
+    println!("  Frequency: {} MHz average", sample.average_freq_mhz);
+    println!("  Usage: {}", percent(sample.usage_percent));
+    println!("  CPU Temperature: {}", temp(sample.cpu_temp_celsius));
+    println!("  Package Power: {}", sample.package_watts.map_or("--".to_string(), |w| format!("{:.1} W", w)));
+    if sample.throttle_events > 0 {
+        println!("  Throttling: {} events", sample.throttle_events);
+    }
+
+    if cores {
+        println!();
+        println!("{:>4}  {:>6}  {:>6}  {:>9}", "CORE", "MHZ", "USAGE", "TEMP");
+        for core in &sample.cores {
+            let freq = core.freq_mhz.map_or("off".to_string(), |freq| freq.to_string());
+            println!("{:>4}  {:>6}  {:>6}  {:>9}", core.core, freq, percent(core.usage_percent), temp(core.temp_celsius));
+        }
+    }
+}
+
+/// Measure power over a short interval for one-shot CLI output
+fn sample_power() -> Result<Vec<backend::energy::ZonePower>> {
+    let mut sampler = backend::EnergySampler::new()?;
+    sampler.sample()?;
+    std::thread::sleep(std::time::Duration::from_millis(250));
+    sampler.sample()?
+        .ok_or_else(|| anyhow::anyhow!("No power sample available"))
+}
+
 fn setup_css() {
     use gtk4::gdk::Display;
     use gtk4::CssProvider;
@@ -0,0 +1,222 @@
+// Control interface shared by the CLI, GUI and daemon
+use anyhow::Result;
+use std::sync::OnceLock;
+
+use crate::backend::profile::FirmwareControls;
+use crate::backend::{CpuManager, PowerManager, ThermalManager};
+use crate::config::ConfigManager;
+use crate::system::dbus::DbusController;
+use crate::system::events::ServiceEvent;
+use crate::system::socket::SocketController;
+use crate::system::status::StatusReport;
+
+/// Operations that change CPU settings. Implemented locally for direct sysfs
+/// access and by the D-Bus client, so callers need not care whether a
+/// daemon is running.
+pub trait Controller: Send + Sync {
+    fn status(&self) -> Result<StatusReport>;
+    fn list_profiles(&self) -> Result<Vec<String>>;
+    fn apply_profile(&self, name: &str) -> Result<()>;
+    fn set_governor(&self, governor: &str) -> Result<()>;
+    fn set_turbo(&self, enabled: bool) -> Result<()>;
+    fn set_frequency_limits(&self, min_mhz: u32, max_mhz: u32) -> Result<()>;
+    fn set_core_online(&self, core: usize, online: bool) -> Result<()>;
+}
+
+/// How a client is able to change settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Changes go through the running service
+    Daemon,
+    /// Running as root, changes are written to sysfs directly
+    Direct,
+    /// No service and no root: settings can be read but not changed
+    ReadOnly,
+}
+
+impl Access {
+    pub fn can_write(self) -> bool {
+        self != Access::ReadOnly
+    }
+}
+
+/// Use the running daemon if there is one (over D-Bus, or its control socket
+/// on hosts without a system bus), otherwise write to sysfs directly
+pub fn connect() -> Result<Box<dyn Controller>> {
+    open().map(|(controller, _)| controller)
+}
+
+/// Like `connect`, but also report whether writes can be expected to work.
+/// A `ReadOnly` controller still serves status and profile queries.
+pub fn open() -> Result<(Box<dyn Controller>, Access)> {
+    match DbusController::connect() {
+        Ok(Some(controller)) => {
+            log::debug!("Using the cpu-power-manager daemon");
+            return Ok((Box::new(controller), Access::Daemon));
+        }
+        Ok(None) => log::debug!("Daemon not on the system bus"),
+        Err(e) => log::debug!("System bus unavailable: {}", e),
+    }
+
+    let socket_path = ConfigManager::system()?.get_config().service.socket.path.clone();
+    match SocketController::connect(&socket_path) {
+        Ok(Some(controller)) => {
+            log::debug!("Using the cpu-power-manager daemon via {}", socket_path);
+            return Ok((Box::new(controller), Access::Daemon));
+        }
+        Ok(None) => log::debug!("Daemon not running, using direct access"),
+        Err(e) => log::debug!("Control socket unavailable, using direct access: {}", e),
+    }
+
+    let access = if nix::unistd::Uid::effective().is_root() { Access::Direct } else { Access::ReadOnly };
+    Ok((Box::new(LocalController::new()?), access))
+}
+
+/// Follow the running daemon's events, over D-Bus or its control socket
+pub fn subscribe() -> Result<Box<dyn Iterator<Item = ServiceEvent>>> {
+    match DbusController::connect() {
+        Ok(Some(controller)) => return Ok(Box::new(controller.subscribe()?)),
+        Ok(None) => log::debug!("Daemon not on the system bus"),
+        Err(e) => log::debug!("System bus unavailable: {}", e),
+    }
+
+    let socket_path = ConfigManager::system()?.get_config().service.socket.path.clone();
+    match SocketController::connect(&socket_path)? {
+        Some(controller) => Ok(Box::new(controller.subscribe()?.map_while(|event| {
+            event.map_err(|e| log::warn!("Lost the service event stream: {}", e)).ok()
+        }))),
+        None => anyhow::bail!("The background service is not running"),
+    }
+}
+
+/// Set scaling limits on all cores after checking them against the hardware range
+pub(crate) fn apply_frequency_limits(cpu_manager: &CpuManager, min_mhz: u32, max_mhz: u32) -> Result<()> {
+    let hw_min = cpu_manager.get_hardware_min_freq(0)?;
+    let hw_max = cpu_manager.get_hardware_max_freq(0)?;
+
+    if min_mhz > max_mhz {
+        anyhow::bail!("Minimum frequency {} MHz is above maximum {} MHz", min_mhz, max_mhz);
+    }
+    if min_mhz < hw_min || max_mhz > hw_max {
+        anyhow::bail!("Frequency limits must be within the hardware range {} - {} MHz", hw_min, hw_max);
+    }
+
+    cpu_manager.set_scaling_limits_all(min_mhz, max_mhz)
+}
+
+/// Direct sysfs access; writes need root
+pub struct LocalController {
+    cpu_manager: CpuManager,
+    thermal_manager: ThermalManager,
+    power_manager: PowerManager,
+    config_manager: ConfigManager,
+    /// Discovered on the first profile change; most commands only read
+    firmware: OnceLock<FirmwareControls>,
+}
+
+impl LocalController {
+    pub fn new() -> Result<Self> {
+        let config_manager = ConfigManager::new()?;
+        let mut thermal_manager = ThermalManager::new()?;
+        thermal_manager.set_cpu_temp_source(config_manager.get_config().thermal.cpu_temp_source.clone());
+
+        Ok(Self {
+            cpu_manager: CpuManager::new()?,
+            thermal_manager,
+            power_manager: PowerManager::new()?,
+            config_manager,
+            firmware: OnceLock::new(),
+        })
+    }
+}
+
+impl Controller for LocalController {
+    fn status(&self) -> Result<StatusReport> {
+        StatusReport::collect(&self.cpu_manager, &self.thermal_manager, &self.power_manager, None)
+    }
+
+    fn list_profiles(&self) -> Result<Vec<String>> {
+        Ok(self.config_manager.profile_names())
+    }
+
+    fn apply_profile(&self, name: &str) -> Result<()> {
+        let firmware = self.firmware.get_or_init(FirmwareControls::new);
+        self.config_manager.get_profile(name)?.apply(&self.cpu_manager, firmware)
+    }
+
+    fn set_governor(&self, governor: &str) -> Result<()> {
+        self.cpu_manager.set_governor_all(governor)
+    }
+
+    fn set_turbo(&self, enabled: bool) -> Result<()> {
+        self.cpu_manager.set_turbo(enabled)
+    }
+
+    fn set_frequency_limits(&self, min_mhz: u32, max_mhz: u32) -> Result<()> {
+        apply_frequency_limits(&self.cpu_manager, min_mhz, max_mhz)
+    }
+
+    fn set_core_online(&self, core: usize, online: bool) -> Result<()> {
+        self.cpu_manager.set_core_online(core, online)
+    }
+}
+
+/// Records every change, for testing the servers without touching sysfs
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct StubController {
+    pub calls: std::sync::Mutex<Vec<String>>,
+}
+
+#[cfg(test)]
+impl StubController {
+    pub const PROFILES: [&str; 4] = ["balanced", "performance", "powersave", "silent"];
+
+    fn record(&self, call: String) -> Result<()> {
+        self.calls.lock().unwrap().push(call);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl Controller for StubController {
+    fn status(&self) -> Result<StatusReport> {
+        Ok(StatusReport {
+            active_profile: Some("balanced".to_string()),
+            governor: "powersave".to_string(),
+            turbo: true,
+            average_freq_mhz: 2400,
+            min_freq_mhz: 800,
+            max_freq_mhz: 4000,
+            cpu_temp_celsius: Some(55.0),
+            on_ac_power: Some(false),
+        })
+    }
+
+    fn list_profiles(&self) -> Result<Vec<String>> {
+        Ok(Self::PROFILES.iter().map(|name| name.to_string()).collect())
+    }
+
+    fn apply_profile(&self, name: &str) -> Result<()> {
+        if !Self::PROFILES.contains(&name) {
+            anyhow::bail!("Profile '{}' not found", name);
+        }
+        self.record(format!("apply_profile {}", name))
+    }
+
+    fn set_governor(&self, governor: &str) -> Result<()> {
+        self.record(format!("set_governor {}", governor))
+    }
+
+    fn set_turbo(&self, enabled: bool) -> Result<()> {
+        self.record(format!("set_turbo {}", enabled))
+    }
+
+    fn set_frequency_limits(&self, min_mhz: u32, max_mhz: u32) -> Result<()> {
+        self.record(format!("set_frequency_limits {} {}", min_mhz, max_mhz))
+    }
+
+    fn set_core_online(&self, core: usize, online: bool) -> Result<()> {
+        self.record(format!("set_core_online {} {}", core, online))
+    }
+}
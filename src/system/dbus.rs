@@ -0,0 +1,399 @@
+// D-Bus system service module
+use anyhow::{Context, Result};
+use std::sync::Arc;
+use zbus::blocking::{connection, fdo::DBusProxy, Connection};
+use zbus::names::BusName;
+use zbus::message::Header;
+use zbus::fdo;
+use zbus::object_server::SignalEmitter;
+
+use crate::system::controller::Controller;
+use crate::system::events::{EventBus, ServiceEvent};
+use crate::system::polkit::{self, Action, Authorizer};
+use crate::system::status::StatusReport;
+
+pub const BUS_NAME: &str = "com.cpupowermanager.Daemon";
+pub const OBJECT_PATH: &str = "/com/cpupowermanager/Daemon";
+pub const INTERFACE: &str = "com.cpupowermanager.Daemon";
+
+struct DaemonInterface {
+    controller: Arc<dyn Controller>,
+    authorizer: Arc<dyn Authorizer>,
+}
+
+fn failed(e: anyhow::Error) -> fdo::Error {
+    fdo::Error::Failed(format!("{:#}", e))
+}
+
+impl DaemonInterface {
+    async fn authorize(&self, header: &Header<'_>, action: Action) -> fdo::Result<()> {
+        polkit::authorize(self.authorizer.as_ref(), Some(header), action).await
+    }
+}
+
+#[zbus::interface(name = "com.cpupowermanager.Daemon")]
+impl DaemonInterface {
+    /// Current status as JSON
+    fn get_status(&self) -> fdo::Result<String> {
+        let status = self.controller.status().map_err(failed)?;
+        serde_json::to_string(&status).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    fn list_profiles(&self) -> fdo::Result<Vec<String>> {
+        self.controller.list_profiles().map_err(failed)
+    }
+
+    async fn apply_profile(&self, #[zbus(header)] header: Header<'_>, name: &str) -> fdo::Result<()> {
+        self.authorize(&header, Action::CpuFreq).await?;
+        self.controller.apply_profile(name).map_err(failed)
+    }
+
+    async fn set_governor(&self, #[zbus(header)] header: Header<'_>, governor: &str) -> fdo::Result<()> {
+        self.authorize(&header, Action::CpuFreq).await?;
+        self.controller.set_governor(governor).map_err(failed)
+    }
+
+    async fn set_turbo(&self, #[zbus(header)] header: Header<'_>, enabled: bool) -> fdo::Result<()> {
+        self.authorize(&header, Action::Turbo).await?;
+        self.controller.set_turbo(enabled).map_err(failed)
+    }
+
+    async fn set_frequency_limits(&self, #[zbus(header)] header: Header<'_>, min_mhz: u32, max_mhz: u32) -> fdo::Result<()> {
+        self.authorize(&header, Action::CpuFreq).await?;
+        self.controller.set_frequency_limits(min_mhz, max_mhz).map_err(failed)
+    }
+
+    async fn set_core_online(&self, #[zbus(header)] header: Header<'_>, core: u32, online: bool) -> fdo::Result<()> {
+        self.authorize(&header, Action::Core).await?;
+        self.controller.set_core_online(core as usize, online).map_err(failed)
+    }
+
+    #[zbus(signal)]
+    async fn profile_changed(emitter: &SignalEmitter<'_>, name: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn power_source_changed(emitter: &SignalEmitter<'_>, on_ac: bool) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn thermal_event(emitter: &SignalEmitter<'_>, description: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn thermal_emergency(emitter: &SignalEmitter<'_>, temp_celsius: f64, max_freq_mhz: u32) -> zbus::Result<()>;
+}
+
+/// Owns `com.cpupowermanager.Daemon` for as long as it is alive
+pub struct DbusServer {
+    connection: Connection,
+}
+
+impl DbusServer {
+    /// Serve on the system bus, or on the bus at `address` (used by tests).
+    /// Methods that change settings are checked with `authorizer` first.
+    pub fn start(
+        controller: Arc<dyn Controller>,
+        authorizer: Arc<dyn Authorizer>,
+        events: &EventBus,
+        address: Option<&str>,
+    ) -> Result<Self> {
+        let builder = match address {
+            Some(address) => connection::Builder::address(address)?,
+            None => connection::Builder::system()?,
+        };
+        let connection = builder
+            .name(BUS_NAME)?
+            .serve_at(OBJECT_PATH, DaemonInterface { controller, authorizer })?
+            .build()
+            .with_context(|| format!("Failed to register {} on the bus", BUS_NAME))?;
+
+        let receiver = events.subscribe();
+        let signal_connection = connection.clone();
+        std::thread::spawn(move || {
+            for event in receiver {
+                if let Err(e) = emit_event(&signal_connection, &event) {
+                    log::warn!("Failed to emit D-Bus signal for {:?}: {}", event, e);
+                }
+            }
+        });
+
+        log::info!("Serving {} on D-Bus", BUS_NAME);
+        Ok(Self { connection })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+}
+
+fn emit_event(connection: &Connection, event: &ServiceEvent) -> zbus::Result<()> {
+    let destination: Option<BusName> = None;
+    match event {
+        ServiceEvent::ProfileChanged { name } => {
+            connection.emit_signal(destination, OBJECT_PATH, INTERFACE, "ProfileChanged", &(name,))
+        }
+        ServiceEvent::PowerSourceChanged { on_ac } => {
+            connection.emit_signal(destination, OBJECT_PATH, INTERFACE, "PowerSourceChanged", &(on_ac,))
+        }
+        ServiceEvent::ThermalEvent { description } => {
+            connection.emit_signal(destination, OBJECT_PATH, INTERFACE, "ThermalEvent", &(description,))
+        }
+        ServiceEvent::ThermalEmergency { temp_celsius, max_freq_mhz } => connection.emit_signal(
+            destination,
+            OBJECT_PATH,
+            INTERFACE,
+            "ThermalEmergency",
+            &(*temp_celsius as f64, max_freq_mhz),
+        ),
+    }
+}
+
+#[zbus::proxy(
+    interface = "com.cpupowermanager.Daemon",
+    default_service = "com.cpupowermanager.Daemon",
+    default_path = "/com/cpupowermanager/Daemon"
+)]
+pub trait Daemon {
+    fn get_status(&self) -> zbus::Result<String>;
+    fn list_profiles(&self) -> zbus::Result<Vec<String>>;
+    fn apply_profile(&self, name: &str) -> zbus::Result<()>;
+    fn set_governor(&self, governor: &str) -> zbus::Result<()>;
+    fn set_turbo(&self, enabled: bool) -> zbus::Result<()>;
+    fn set_frequency_limits(&self, min_mhz: u32, max_mhz: u32) -> zbus::Result<()>;
+    fn set_core_online(&self, core: u32, online: bool) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn profile_changed(&self, name: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn power_source_changed(&self, on_ac: bool) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn thermal_event(&self, description: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn thermal_emergency(&self, temp_celsius: f64, max_freq_mhz: u32) -> zbus::Result<()>;
+}
+
+/// Client for a running daemon
+pub struct DbusController {
+    proxy: DaemonProxyBlocking<'static>,
+}
+
+impl DbusController {
+    /// Connect to the daemon on the system bus. Returns `None` if it is not running.
+    pub fn connect() -> Result<Option<Self>> {
+        Self::connect_with(Connection::system()?)
+    }
+
+    pub fn connect_with(connection: Connection) -> Result<Option<Self>> {
+        let name = BusName::try_from(BUS_NAME)?;
+        if !DBusProxy::new(&connection)?.name_has_owner(name)? {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            proxy: DaemonProxyBlocking::new(&connection)?,
+        }))
+    }
+
+    pub fn proxy(&self) -> &DaemonProxyBlocking<'static> {
+        &self.proxy
+    }
+
+    /// Service events, read from the daemon's signals
+    pub fn subscribe(&self) -> Result<impl Iterator<Item = ServiceEvent> + use<>> {
+        Ok(self.proxy.inner().receive_all_signals()?.filter_map(|message| {
+            let header = message.header();
+            let body = message.body();
+            let event = match header.member()?.as_str() {
+                "ProfileChanged" => body.deserialize().map(|(name,)| ServiceEvent::ProfileChanged { name }),
+                "PowerSourceChanged" => body.deserialize().map(|(on_ac,)| ServiceEvent::PowerSourceChanged { on_ac }),
+                "ThermalEvent" => body.deserialize().map(|(description,)| ServiceEvent::ThermalEvent { description }),
+                "ThermalEmergency" => body.deserialize().map(|(temp_celsius, max_freq_mhz): (f64, u32)| {
+                    ServiceEvent::ThermalEmergency { temp_celsius: temp_celsius as f32, max_freq_mhz }
+                }),
+                _ => return None,
+            };
+            event.map_err(|e| log::debug!("Ignoring malformed daemon signal: {}", e)).ok()
+        }))
+    }
+}
+
+/// Surface the daemon's error message rather than the D-Bus error name
+fn call_error(e: zbus::Error) -> anyhow::Error {
+    match e {
+        zbus::Error::MethodError(name, Some(message), _) if name.as_str() == "org.freedesktop.DBus.Error.AccessDenied" => {
+            anyhow::anyhow!("{}. Check the PolicyKit rules for cpu-power-manager.", message)
+        }
+        zbus::Error::MethodError(_, Some(message), _) => anyhow::anyhow!(message),
+        e => e.into(),
+    }
+}
+
+impl Controller for DbusController {
+    fn status(&self) -> Result<StatusReport> {
+        let json = self.proxy.get_status().map_err(call_error)?;
+        serde_json::from_str(&json).context("Failed to parse daemon status")
+    }
+
+    fn list_profiles(&self) -> Result<Vec<String>> {
+        self.proxy.list_profiles().map_err(call_error)
+    }
+
+    fn apply_profile(&self, name: &str) -> Result<()> {
+        self.proxy.apply_profile(name).map_err(call_error)
+    }
+
+    fn set_governor(&self, governor: &str) -> Result<()> {
+        self.proxy.set_governor(governor).map_err(call_error)
+    }
+
+    fn set_turbo(&self, enabled: bool) -> Result<()> {
+        self.proxy.set_turbo(enabled).map_err(call_error)
+    }
+
+    fn set_frequency_limits(&self, min_mhz: u32, max_mhz: u32) -> Result<()> {
+        self.proxy.set_frequency_limits(min_mhz, max_mhz).map_err(call_error)
+    }
+
+    fn set_core_online(&self, core: usize, online: bool) -> Result<()> {
+        self.proxy.set_core_online(core as u32, online).map_err(call_error)
+    }
+}
+
+/// Private `dbus-daemon` for tests, killed on drop even if the test panics
+#[cfg(test)]
+pub(crate) struct TestBus {
+    daemon: std::process::Child,
+    pub address: String,
+}
+
+#[cfg(test)]
+impl TestBus {
+    /// `None` if dbus-daemon is not installed
+    pub fn spawn() -> Option<Self> {
+        use std::io::{BufRead, BufReader};
+        use std::process::{Command, Stdio};
+
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .ok()?;
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap()).read_line(&mut address).unwrap();
+        Some(Self { daemon, address: address.trim().to_string() })
+    }
+
+    pub fn connect(&self) -> Connection {
+        connection::Builder::address(self.address.as_str()).unwrap().build().unwrap()
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::controller::StubController;
+    use crate::system::polkit::{AuthorizationFuture, StubAuthorizer};
+
+    /// Never answers, like polkit while an authentication dialog is open
+    struct PendingAuthorizer;
+
+    impl Authorizer for PendingAuthorizer {
+        fn check<'a>(&'a self, _sender: &'a str, _action: Action) -> AuthorizationFuture<'a> {
+            Box::pin(std::future::pending())
+        }
+    }
+
+    fn connect_client(bus: &TestBus) -> DbusController {
+        DbusController::connect_with(bus.connect()).unwrap().expect("daemon is registered")
+    }
+
+    #[test]
+    fn test_daemon_on_private_bus() {
+        let Some(bus) = TestBus::spawn() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+
+        let stub = Arc::new(StubController::default());
+        let authorizer = StubAuthorizer::new(true);
+        let events = EventBus::new();
+        let _server = DbusServer::start(stub.clone(), authorizer.clone(), &events, Some(&bus.address)).unwrap();
+        let controller = connect_client(&bus);
+
+        assert_eq!(controller.list_profiles().unwrap(), StubController::PROFILES);
+        assert_eq!(controller.status().unwrap().max_freq_mhz, 4000);
+
+        controller.apply_profile("silent").unwrap();
+        controller.set_turbo(false).unwrap();
+        controller.set_frequency_limits(800, 3000).unwrap();
+        let error = controller.apply_profile("turbo").unwrap_err();
+        assert_eq!(error.to_string(), "Profile 'turbo' not found");
+        assert_eq!(
+            *stub.calls.lock().unwrap(),
+            vec!["apply_profile silent", "set_turbo false", "set_frequency_limits 800 3000"]
+        );
+        assert_eq!(
+            *authorizer.checks.lock().unwrap(),
+            vec![Action::CpuFreq, Action::Turbo, Action::CpuFreq, Action::CpuFreq]
+        );
+
+        let mut signals = controller.proxy().receive_profile_changed().unwrap();
+        events.publish(ServiceEvent::ProfileChanged { name: "Silent".to_string() });
+        let signal = signals.next().unwrap();
+        assert_eq!(signal.args().unwrap().name, "Silent");
+
+        let mut received = controller.subscribe().unwrap();
+        let emergency = ServiceEvent::ThermalEmergency { temp_celsius: 98.5, max_freq_mhz: 800 };
+        events.publish(emergency.clone());
+        assert_eq!(received.next().unwrap(), emergency);
+    }
+
+    #[test]
+    fn test_unauthorized_callers_are_denied() {
+        let Some(bus) = TestBus::spawn() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+
+        let stub = Arc::new(StubController::default());
+        let authorizer = StubAuthorizer::new(false);
+        let _server = DbusServer::start(stub.clone(), authorizer.clone(), &EventBus::new(), Some(&bus.address)).unwrap();
+        let controller = connect_client(&bus);
+
+        // Reads need no authorization
+        assert_eq!(controller.list_profiles().unwrap().len(), StubController::PROFILES.len());
+        assert!(controller.set_governor("performance").is_err());
+        let error = controller.set_core_online(3, false).unwrap_err();
+        assert!(error.to_string().contains("com.cpupowermanager.setcore"));
+        assert!(stub.calls.lock().unwrap().is_empty());
+        assert_eq!(*authorizer.checks.lock().unwrap(), vec![Action::CpuFreq, Action::Core]);
+    }
+
+    #[test]
+    fn test_pending_authorization_does_not_block_other_callers() {
+        let Some(bus) = TestBus::spawn() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+
+        let stub = Arc::new(StubController::default());
+        let _server = DbusServer::start(stub.clone(), Arc::new(PendingAuthorizer), &EventBus::new(), Some(&bus.address)).unwrap();
+
+        let waiting = connect_client(&bus);
+        std::thread::spawn(move || waiting.set_turbo(false));
+        std::thread::sleep(std::time::Duration::from_millis(200));
+
+        assert_eq!(connect_client(&bus).list_profiles().unwrap().len(), StubController::PROFILES.len());
+        assert!(stub.calls.lock().unwrap().is_empty());
+    }
+}
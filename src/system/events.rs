@@ -0,0 +1,43 @@
+// Service event broadcasting module
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+/// Something the service did or noticed that clients may want to react to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ServiceEvent {
+    ProfileChanged { name: String },
+    PowerSourceChanged { on_ac: bool },
+    ThermalEvent { description: String },
+    /// Thermal protection dropped to the hardware minimum. Published for the
+    /// GUI to notify the desktop user, which the service itself cannot.
+    ThermalEmergency { temp_celsius: f32, max_freq_mhz: u32 },
+}
+
+/// Fans service events out to any number of subscribers (D-Bus signals,
+/// socket clients, ...). Subscribers that went away are dropped on publish.
+#[derive(Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<Sender<ServiceEvent>>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&self) -> Receiver<ServiceEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    pub fn publish(&self, event: ServiceEvent) {
+        log::debug!("Service event: {:?}", event);
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
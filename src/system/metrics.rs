@@ -0,0 +1,336 @@
+// Prometheus metrics export module
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::backend::cpu::CoreStatus;
+use crate::backend::thermal::ThermalZone;
+use crate::backend::throttle::ThrottleCounts;
+use crate::backend::EnergySampler;
+use crate::config::MetricsConfig;
+
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What the service knows at one tick, in the shape it is exported
+#[derive(Debug, Clone, Default)]
+pub struct MetricsSnapshot {
+    pub cores: Vec<CoreStatus>,
+    pub turbo: Option<bool>,
+    pub cpu_temp_celsius: Option<f32>,
+    pub thermal_zones: Vec<ThermalZone>,
+    pub package_watts: Option<f32>,
+    pub active_profile: Option<String>,
+    pub on_ac_power: Option<bool>,
+    pub auto_tune_enabled: bool,
+    /// Profile the user picked, which auto-tuning leaves alone until the power source changes
+    pub manual_override: Option<String>,
+    pub thermal_protection_active: bool,
+    /// Throttle events detected since the service started
+    pub throttle_events: ThrottleCounts,
+    /// Kernel thermal throttle counters since boot
+    pub kernel_throttle: ThrottleCounts,
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Prometheus text exposition format writer
+#[derive(Default)]
+struct Exposition {
+    text: String,
+}
+
+impl Exposition {
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.text, "# HELP cpu_power_manager_{} {}", name, help);
+        let _ = writeln!(self.text, "# TYPE cpu_power_manager_{} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        let _ = write!(self.text, "cpu_power_manager_{}", name);
+        if !labels.is_empty() {
+            let labels: Vec<String> = labels
+                .iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, escape_label(value)))
+                .collect();
+            let _ = write!(self.text, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.text, " {}", value);
+    }
+
+    fn gauge(&mut self, name: &str, help: &str, value: Option<f64>) {
+        if let Some(value) = value {
+            self.family(name, "gauge", help);
+            self.sample(name, &[], value);
+        }
+    }
+}
+
+fn flag(value: bool) -> f64 {
+    if value { 1.0 } else { 0.0 }
+}
+
+pub fn render(snapshot: &MetricsSnapshot) -> String {
+    let mut out = Exposition::default();
+
+    out.family("core_frequency_hertz", "gauge", "Current core frequency");
+    for core in snapshot.cores.iter().filter(|core| core.online) {
+        let id = core.core_id.to_string();
+        out.sample(
+            "core_frequency_hertz",
+            &[("core", &id), ("governor", &core.governor)],
+            core.current_freq as f64 * 1e6,
+        );
+    }
+    out.family("core_online", "gauge", "Whether the core is online");
+    for core in &snapshot.cores {
+        out.sample("core_online", &[("core", &core.core_id.to_string())], flag(core.online));
+    }
+    if snapshot.cores.iter().any(|core| core.temperature_celsius.is_some()) {
+        out.family("core_temperature_celsius", "gauge", "Core temperature from hwmon");
+        for core in &snapshot.cores {
+            if let Some(temp) = core.temperature_celsius {
+                out.sample("core_temperature_celsius", &[("core", &core.core_id.to_string())], temp as f64);
+            }
+        }
+    }
+
+    out.gauge("turbo_enabled", "Whether turbo boost is enabled", snapshot.turbo.map(flag));
+    out.gauge("cpu_temperature_celsius", "CPU temperature", snapshot.cpu_temp_celsius.map(f64::from));
+    if !snapshot.thermal_zones.is_empty() {
+        out.family("thermal_zone_temperature_celsius", "gauge", "Thermal zone temperature");
+        for zone in &snapshot.thermal_zones {
+            out.sample(
+                "thermal_zone_temperature_celsius",
+                &[("zone", &zone.id.to_string()), ("type", &zone.type_name)],
+                zone.temp_celsius as f64,
+            );
+        }
+    }
+    out.gauge("package_power_watts", "Package power over the last interval", snapshot.package_watts.map(f64::from));
+    out.gauge("on_ac_power", "Whether the system runs on external power", snapshot.on_ac_power.map(flag));
+
+    if let Some(profile) = &snapshot.active_profile {
+        out.family("active_profile", "gauge", "Profile applied by the service");
+        out.sample("active_profile", &[("profile", profile)], 1.0);
+    }
+    out.gauge("auto_tune_enabled", "Whether profiles follow the power source", Some(flag(snapshot.auto_tune_enabled)));
+    if let Some(profile) = &snapshot.manual_override {
+        out.family("auto_tune_manual_override", "gauge", "Profile picked by the user that auto-tuning keeps");
+        out.sample("auto_tune_manual_override", &[("profile", profile)], 1.0);
+    }
+    out.gauge(
+        "thermal_protection_active",
+        "Whether thermal protection is holding frequency limits down",
+        Some(flag(snapshot.thermal_protection_active)),
+    );
+
+    let events = &snapshot.throttle_events;
+    out.family("throttle_events_total", "counter", "Throttle events detected by the service");
+    for (cause, count) in [
+        ("core_thermal", events.core_thermal),
+        ("package_thermal", events.package_thermal),
+        ("frequency_limited", events.frequency_limited),
+        ("cooling_device", events.cooling_device),
+    ] {
+        out.sample("throttle_events_total", &[("cause", cause)], count as f64);
+    }
+    out.family("kernel_throttle_total", "counter", "Kernel thermal throttle counters since boot");
+    out.sample("kernel_throttle_total", &[("scope", "core")], snapshot.kernel_throttle.core_thermal as f64);
+    out.sample("kernel_throttle_total", &[("scope", "package")], snapshot.kernel_throttle.package_thermal as f64);
+
+    out.text
+}
+
+/// Publishes rendered metrics over HTTP on localhost and/or to a
+/// node_exporter textfile
+pub struct MetricsExporter {
+    latest: Arc<Mutex<String>>,
+    textfile: Option<PathBuf>,
+    energy_sampler: Option<EnergySampler>,
+    address: Option<SocketAddr>,
+}
+
+impl MetricsExporter {
+    /// Returns `None` when neither a port nor a textfile is configured
+    pub fn start(config: &MetricsConfig) -> Result<Option<Self>> {
+        if config.port.is_none() && config.textfile.is_none() {
+            return Ok(None);
+        }
+
+        let latest = Arc::new(Mutex::new(String::new()));
+        let address = match config.port {
+            Some(port) => {
+                let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))
+                    .with_context(|| format!("Failed to listen on port {}", port))?;
+                let address = listener.local_addr()?;
+                log::info!("Serving metrics on http://{}/metrics", address);
+
+                let latest = latest.clone();
+                thread::spawn(move || {
+                    for stream in listener.incoming().filter_map(|stream| stream.ok()) {
+                        let latest = latest.clone();
+                        thread::spawn(move || {
+                            if let Err(e) = Self::serve(stream, &latest) {
+                                log::debug!("Metrics request failed: {}", e);
+                            }
+                        });
+                    }
+                });
+                Some(address)
+            }
+            None => None,
+        };
+
+        let energy_sampler = match EnergySampler::new() {
+            Ok(sampler) => Some(sampler),
+            Err(e) => {
+                log::debug!("Package power will not be exported: {}", e);
+                None
+            }
+        };
+
+        Ok(Some(Self {
+            latest,
+            textfile: config.textfile.as_ref().map(PathBuf::from),
+            energy_sampler,
+            address,
+        }))
+    }
+
+    /// Address the HTTP endpoint is bound to
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.address
+    }
+
+    /// Package power since the previous call
+    pub fn sample_package_watts(&mut self) -> Option<f32> {
+        let zones = self.energy_sampler.as_mut()?.sample().ok()??;
+        EnergySampler::package_watts(&zones)
+    }
+
+    pub fn publish(&self, snapshot: &MetricsSnapshot) {
+        let text = render(snapshot);
+
+        // Write and rename so node_exporter never reads a partial file
+        if let Some(path) = &self.textfile {
+            let tmp = path.with_extension("prom.tmp");
+            if let Err(e) = fs::write(&tmp, &text).and_then(|_| fs::rename(&tmp, path)) {
+                log::warn!("Failed to write metrics to {}: {}", path.display(), e);
+            }
+        }
+
+        *self.latest.lock().unwrap() = text;
+    }
+
+    fn serve(mut stream: TcpStream, latest: &Mutex<String>) -> Result<()> {
+        // Each client has its own thread; this ends the ones that stop sending
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+        let mut request_line = String::new();
+        let mut reader = BufReader::new(stream.try_clone()?);
+        reader.read_line(&mut request_line)?;
+        // Drain the headers
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 2 {
+            line.clear();
+        }
+
+        let path = request_line.split_whitespace().nth(1).unwrap_or_default();
+        let (status, body) = if request_line.starts_with("GET ") && (path == "/metrics" || path == "/") {
+            ("200 OK", latest.lock().unwrap().clone())
+        } else {
+            ("404 Not Found", "Not found\n".to_string())
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            CONTENT_TYPE,
+            body.len(),
+            body
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::TempDir;
+    use std::io::Read;
+
+    fn snapshot() -> MetricsSnapshot {
+        MetricsSnapshot {
+            cores: vec![CoreStatus {
+                core_id: 0,
+                current_freq: 2400,
+                min_freq: 800,
+                max_freq: 4000,
+                governor: "powersave".to_string(),
+                online: true,
+                usage_percent: 0.0,
+                temperature_celsius: Some(55.0),
+            }],
+            turbo: Some(true),
+            package_watts: Some(12.5),
+            active_profile: Some("quiet \"night\"".to_string()),
+            throttle_events: ThrottleCounts { package_thermal: 3, ..ThrottleCounts::default() },
+            ..MetricsSnapshot::default()
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let text = render(&snapshot());
+
+        assert!(text.contains("# TYPE cpu_power_manager_core_frequency_hertz gauge\n"));
+        assert!(text.contains("cpu_power_manager_core_frequency_hertz{core=\"0\",governor=\"powersave\"} 2400000000\n"));
+        assert!(text.contains("cpu_power_manager_turbo_enabled 1\n"));
+        assert!(text.contains("cpu_power_manager_package_power_watts 12.5\n"));
+        assert!(text.contains("cpu_power_manager_active_profile{profile=\"quiet \\\"night\\\"\"} 1\n"));
+        assert!(text.contains("cpu_power_manager_throttle_events_total{cause=\"package_thermal\"} 3\n"));
+        // Unknown readings are left out rather than reported as zero
+        assert!(!text.contains("cpu_power_manager_cpu_temperature_celsius"));
+    }
+
+    #[test]
+    fn test_http_and_textfile() {
+        let dir = TempDir::new("metrics");
+        let textfile = dir.join("cpu-power-manager.prom");
+        let config = MetricsConfig {
+            port: Some(0),
+            textfile: Some(textfile.to_string_lossy().into_owned()),
+        };
+        let exporter = MetricsExporter::start(&config).unwrap().unwrap();
+        exporter.publish(&snapshot());
+
+        let written = fs::read_to_string(&textfile).unwrap();
+        assert!(written.contains("cpu_power_manager_turbo_enabled 1"));
+
+        let fetch = |path: &str| {
+            let mut stream = TcpStream::connect(exporter.address().unwrap()).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+        // A client that never sends its request does not hold up the others
+        let _idle = TcpStream::connect(exporter.address().unwrap()).unwrap();
+        let response = fetch("/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(&written));
+        assert!(fetch("/other").starts_with("HTTP/1.1 404"));
+
+        assert!(MetricsExporter::start(&MetricsConfig::default()).unwrap().is_none());
+    }
+}
@@ -1 +1,15 @@
-// System integration stubs
+// System integration modules
+pub mod controller;
+pub mod dbus;
+pub mod events;
+pub mod metrics;
+pub mod monitor;
+pub mod polkit;
+pub mod power_profiles;
+pub mod recording;
+pub mod service;
+pub mod socket;
+pub mod status;
+pub mod uevent;
+
+pub use service::Service;
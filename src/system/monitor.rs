@@ -0,0 +1,339 @@
+// Periodic sampling module for the monitor and record commands
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use crate::backend::load::CpuLoadSampler;
+use crate::backend::{CpuManager, EnergySampler, ThermalManager, ThrottleMonitor};
+use crate::config::ConfigManager;
+use crate::system::controller::{self, Access, Controller};
+
+/// One core's readings at a point in time; offline cores have none
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoreSample {
+    pub core: usize,
+    /// `None` while the core is offline
+    pub freq_mhz: Option<u32>,
+    pub usage_percent: Option<f32>,
+    pub temp_celsius: Option<f32>,
+}
+
+/// System-wide readings at a point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sample {
+    pub timestamp: DateTime<Local>,
+    /// Active profile, known only when the service is running
+    pub profile: Option<String>,
+    pub governor: String,
+    pub turbo: Option<bool>,
+    pub average_freq_mhz: u32,
+    pub usage_percent: Option<f32>,
+    pub cpu_temp_celsius: Option<f32>,
+    pub package_watts: Option<f32>,
+    /// Throttle events detected since the previous sample
+    pub throttle_events: usize,
+    pub cores: Vec<CoreSample>,
+}
+
+impl Sample {
+    /// CSV header matching `csv_row`; per-core columns are added when `cores` is set.
+    /// Offline cores keep their columns, so the header stays valid when they come back.
+    pub fn csv_header(&self, cores: bool) -> String {
+        let mut columns = vec![
+            "timestamp", "profile", "governor", "turbo", "average_freq_mhz", "usage_percent", "cpu_temp_celsius",
+            "package_watts", "throttle_events",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect::<Vec<_>>();
+
+        if cores {
+            for core in &self.cores {
+                columns.push(format!("core{}_freq_mhz", core.core));
+                columns.push(format!("core{}_usage_percent", core.core));
+                columns.push(format!("core{}_temp_celsius", core.core));
+            }
+        }
+        csv_join(&columns)
+    }
+
+    /// Missing readings are left empty. Fields are quoted as RFC 4180 requires,
+    /// since profile names are free text.
+    pub fn csv_row(&self, cores: bool) -> String {
+        let opt = |value: Option<f32>| value.map_or(String::new(), |v| format!("{:.1}", v));
+
+        let mut fields = vec![
+            self.timestamp.to_rfc3339(),
+            self.profile.clone().unwrap_or_default(),
+            self.governor.clone(),
+            self.turbo.map_or(String::new(), |t| t.to_string()),
+            self.average_freq_mhz.to_string(),
+            opt(self.usage_percent),
+            opt(self.cpu_temp_celsius),
+            opt(self.package_watts),
+            self.throttle_events.to_string(),
+        ];
+
+        if cores {
+            for core in &self.cores {
+                fields.push(core.freq_mhz.map_or(String::new(), |freq| freq.to_string()));
+                fields.push(opt(core.usage_percent));
+                fields.push(opt(core.temp_celsius));
+            }
+        }
+        csv_join(&fields)
+    }
+
+    /// Parse a row written by `csv_row`, given the header it was written with
+    pub fn from_csv(header: &str, row: &str) -> Result<Self> {
+        let columns = csv_split(header)?;
+        let fields = csv_split(row)?;
+        if columns.len() != fields.len() {
+            anyhow::bail!("Expected {} fields, found {}", columns.len(), fields.len());
+        }
+        let field = |name: &str| -> Result<&str> {
+            columns
+                .iter()
+                .position(|column| column == name)
+                .map(|i| fields[i].as_str())
+                .with_context(|| format!("Missing column {}", name))
+        };
+        fn opt<T: std::str::FromStr>(value: &str) -> Option<T> {
+            value.parse().ok()
+        }
+
+        let cores = columns
+            .iter()
+            .filter_map(|column| column.strip_prefix("core")?.strip_suffix("_freq_mhz")?.parse::<usize>().ok())
+            .map(|core| {
+                Ok(CoreSample {
+                    core,
+                    freq_mhz: opt(field(&format!("core{}_freq_mhz", core))?),
+                    usage_percent: opt(field(&format!("core{}_usage_percent", core))?),
+                    temp_celsius: opt(field(&format!("core{}_temp_celsius", core))?),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            timestamp: DateTime::parse_from_rfc3339(field("timestamp")?)?.with_timezone(&Local),
+            profile: Some(field("profile")?).filter(|p| !p.is_empty()).map(String::from),
+            governor: field("governor")?.to_string(),
+            turbo: opt(field("turbo")?),
+            average_freq_mhz: field("average_freq_mhz")?.parse()?,
+            usage_percent: opt(field("usage_percent")?),
+            cpu_temp_celsius: opt(field("cpu_temp_celsius")?),
+            package_watts: opt(field("package_watts")?),
+            throttle_events: field("throttle_events")?.parse()?,
+            cores,
+        })
+    }
+}
+
+/// One CSV row, quoting fields that contain a comma or quote. Line breaks
+/// become spaces so every row stays on one line.
+fn csv_join(fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            let field = field.replace(['\r', '\n'], " ");
+            if field.contains([',', '"']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Split a row written by `csv_join`
+fn csv_split(row: &str) -> Result<Vec<String>> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = row.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => fields.push(std::mem::take(&mut field)),
+            (_, c) => field.push(c),
+        }
+    }
+    if quoted {
+        anyhow::bail!("Unterminated quoted field");
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+/// Takes `Sample`s. Usage, package power and throttling cover the time
+/// since the previous sample; the constructor records the baseline.
+pub struct Monitor {
+    cpu_manager: CpuManager,
+    thermal_manager: ThermalManager,
+    load_sampler: CpuLoadSampler,
+    energy_sampler: Option<EnergySampler>,
+    throttle_monitor: ThrottleMonitor,
+    daemon: Option<Box<dyn Controller>>,
+}
+
+impl Monitor {
+    pub fn new(config_manager: &ConfigManager) -> Result<Self> {
+        let mut thermal_manager = ThermalManager::new()?;
+        thermal_manager.set_cpu_temp_source(config_manager.get_config().thermal.cpu_temp_source.clone());
+
+        let mut load_sampler = CpuLoadSampler::new();
+        load_sampler.sample()?;
+
+        let energy_sampler = match EnergySampler::new() {
+            Ok(mut sampler) => sampler.sample().map(|_| sampler).ok(),
+            Err(e) => {
+                log::debug!("Package power unavailable: {}", e);
+                None
+            }
+        };
+
+        let cpu_manager = CpuManager::new()?;
+        let mut throttle_monitor = ThrottleMonitor::new();
+        throttle_monitor.poll(&cpu_manager, &thermal_manager);
+
+        let daemon = match controller::open() {
+            Ok((controller, Access::Daemon)) => Some(controller),
+            _ => None,
+        };
+
+        Ok(Self {
+            cpu_manager,
+            thermal_manager,
+            load_sampler,
+            energy_sampler,
+            throttle_monitor,
+            daemon,
+        })
+    }
+
+    pub fn sample(&mut self) -> Result<Sample> {
+        let usage = self.load_sampler.sample()?;
+        let package_watts = self
+            .energy_sampler
+            .as_mut()
+            .and_then(|sampler| sampler.sample().ok().flatten())
+            .and_then(|zones| EnergySampler::package_watts(&zones));
+        let throttle_events = self.throttle_monitor.poll(&self.cpu_manager, &self.thermal_manager).len();
+        let profile = self
+            .daemon
+            .as_ref()
+            .and_then(|daemon| daemon.status().ok())
+            .and_then(|status| status.active_profile);
+
+        let cores: Vec<CoreSample> = self
+            .cpu_manager
+            .get_all_core_status()?
+            .into_iter()
+            .map(|status| {
+                if !status.online {
+                    return CoreSample { core: status.core_id, freq_mhz: None, usage_percent: None, temp_celsius: None };
+                }
+                CoreSample {
+                    core: status.core_id,
+                    freq_mhz: Some(status.current_freq),
+                    usage_percent: usage.get(&status.core_id).copied(),
+                    temp_celsius: status.temperature_celsius,
+                }
+            })
+            .collect();
+        let online: Vec<u32> = cores.iter().filter_map(|c| c.freq_mhz).collect();
+
+        let average = |values: Vec<f32>| (!values.is_empty()).then(|| values.iter().sum::<f32>() / values.len() as f32);
+
+        Ok(Sample {
+            timestamp: Local::now(),
+            profile,
+            governor: self.cpu_manager.get_governor(0)?,
+            turbo: self.cpu_manager.is_turbo_enabled().ok(),
+            average_freq_mhz: online.iter().sum::<u32>() / online.len().max(1) as u32,
+            usage_percent: average(cores.iter().filter_map(|c| c.usage_percent).collect()),
+            cpu_temp_celsius: self.thermal_manager.get_cpu_temperature().ok(),
+            package_watts,
+            throttle_events,
+            cores,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Sample {
+        Sample {
+            timestamp: Local::now(),
+            profile: None,
+            governor: "powersave".to_string(),
+            turbo: Some(true),
+            average_freq_mhz: 2000,
+            usage_percent: Some(25.0),
+            cpu_temp_celsius: None,
+            package_watts: Some(7.5),
+            throttle_events: 1,
+            cores: vec![
+                CoreSample { core: 0, freq_mhz: Some(1800), usage_percent: Some(30.0), temp_celsius: Some(50.0) },
+                CoreSample { core: 1, freq_mhz: None, usage_percent: None, temp_celsius: None },
+                CoreSample { core: 2, freq_mhz: Some(2200), usage_percent: None, temp_celsius: None },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_csv_columns_match() {
+        let sample = sample();
+
+        for cores in [false, true] {
+            let header = sample.csv_header(cores);
+            let row = sample.csv_row(cores);
+            assert_eq!(header.split(',').count(), row.split(',').count());
+        }
+
+        assert!(sample.csv_header(true).ends_with("core2_freq_mhz,core2_usage_percent,core2_temp_celsius"));
+        let row = sample.csv_row(true);
+        assert!(row.ends_with(",,powersave,true,2000,25.0,,7.5,1,1800,30.0,50.0,,,,2200,,"), "{}", row);
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let mut sample = sample();
+        sample.profile = Some("silent".to_string());
+
+        let parsed = Sample::from_csv(&sample.csv_header(true), &sample.csv_row(true)).unwrap();
+        assert_eq!(parsed.profile.as_deref(), Some("silent"));
+        assert_eq!(parsed.timestamp.timestamp(), sample.timestamp.timestamp());
+        assert_eq!(parsed.package_watts, Some(7.5));
+        assert_eq!(parsed.cores.len(), 3);
+        assert_eq!(parsed.cores[1].freq_mhz, None);
+        assert_eq!(parsed.cores[2].core, 2);
+        assert_eq!(parsed.cores[2].freq_mhz, Some(2200));
+        assert_eq!(parsed.cores[2].usage_percent, None);
+
+        assert!(Sample::from_csv(&sample.csv_header(false), &sample.csv_row(true)).is_err());
+    }
+
+    #[test]
+    fn test_csv_quotes_free_text() {
+        let mut sample = sample();
+        sample.profile = Some("Quiet, \"night\" mode".to_string());
+
+        let row = sample.csv_row(false);
+        assert!(row.contains(r#","Quiet, ""night"" mode",powersave,"#), "{}", row);
+        let parsed = Sample::from_csv(&sample.csv_header(false), &row).unwrap();
+        assert_eq!(parsed.profile, sample.profile);
+        assert_eq!(parsed.governor, "powersave");
+
+        assert!(Sample::from_csv(&sample.csv_header(false), &row.replace(" mode\"", " mode")).is_err());
+    }
+}
@@ -0,0 +1,210 @@
+// PolicyKit authorization module
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use zbus::blocking::{fdo::DBusProxy, Connection};
+use zbus::fdo;
+use zbus::message::Header;
+use zbus::names::BusName;
+use zbus::zvariant::Value;
+
+/// Actions declared in `com.cpupowermanager.policy` (`set<action>`), and the
+/// power-profiles-daemon ones in `org.freedesktop.UPower.PowerProfiles.policy`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    CpuFreq,
+    Turbo,
+    Core,
+    SwitchProfile,
+    HoldProfile,
+}
+
+impl Action {
+    pub fn id(self) -> &'static str {
+        match self {
+            Action::CpuFreq => "com.cpupowermanager.setcpufreq",
+            Action::Turbo => "com.cpupowermanager.setturbo",
+            Action::Core => "com.cpupowermanager.setcore",
+            Action::SwitchProfile => "org.freedesktop.UPower.PowerProfiles.switch-profile",
+            Action::HoldProfile => "org.freedesktop.UPower.PowerProfiles.hold-profile",
+        }
+    }
+}
+
+pub type AuthorizationFuture<'a> = Pin<Box<dyn Future<Output = Result<bool>> + Send + 'a>>;
+
+/// Decides whether a D-Bus caller may perform a privileged action. Checks
+/// are asynchronous: polkit may wait for the user to answer a password prompt.
+pub trait Authorizer: Send + Sync {
+    /// `sender` is the caller's unique bus name, e.g. `:1.42`
+    fn check<'a>(&'a self, sender: &'a str, action: Action) -> AuthorizationFuture<'a>;
+}
+
+const AUTHORITY_NAME: &str = "org.freedesktop.PolicyKit1";
+
+/// Check the sender of a D-Bus call, mapping a denial to `AccessDenied`.
+/// Awaits polkit, so other callers are served while an authentication dialog is open.
+pub async fn authorize(authorizer: &dyn Authorizer, header: Option<&Header<'_>>, action: Action) -> fdo::Result<()> {
+    let sender = header
+        .and_then(|header| header.sender())
+        .ok_or_else(|| fdo::Error::AccessDenied("Caller has no bus name".to_string()))?;
+
+    match authorizer.check(sender.as_str(), action).await {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            log::warn!("Denied {} to {}", action.id(), sender);
+            Err(fdo::Error::AccessDenied(format!("Not authorized for {}", action.id())))
+        }
+        Err(e) => Err(fdo::Error::Failed(format!("{:#}", e))),
+    }
+}
+
+/// Grants or denies everything, recording what was asked
+#[cfg(test)]
+pub(crate) struct StubAuthorizer {
+    allow: bool,
+    pub checks: std::sync::Mutex<Vec<Action>>,
+}
+
+#[cfg(test)]
+impl StubAuthorizer {
+    pub fn new(allow: bool) -> std::sync::Arc<Self> {
+        std::sync::Arc::new(Self { allow, checks: std::sync::Mutex::new(vec![]) })
+    }
+}
+
+#[cfg(test)]
+impl Authorizer for StubAuthorizer {
+    fn check<'a>(&'a self, sender: &'a str, action: Action) -> AuthorizationFuture<'a> {
+        assert!(sender.starts_with(':'));
+        self.checks.lock().unwrap().push(action);
+        Box::pin(std::future::ready(Ok(self.allow)))
+    }
+}
+
+/// `CheckAuthorizationFlags::AllowUserInteraction`: let the caller's polkit agent prompt for a password
+const ALLOW_USER_INTERACTION: u32 = 1;
+
+#[zbus::proxy(
+    interface = "org.freedesktop.PolicyKit1.Authority",
+    default_service = "org.freedesktop.PolicyKit1",
+    default_path = "/org/freedesktop/PolicyKit1/Authority"
+)]
+trait Authority {
+    fn check_authorization(
+        &self,
+        subject: &(&str, HashMap<&str, Value<'_>>),
+        action_id: &str,
+        details: HashMap<&str, &str>,
+        flags: u32,
+        cancellation_id: &str,
+    ) -> zbus::Result<(bool, bool, HashMap<String, String>)>;
+}
+
+/// Asks polkitd via `org.freedesktop.PolicyKit1.Authority.CheckAuthorization`.
+///
+/// Uses its own connection: the check may wait on an authentication dialog,
+/// and the reply must not queue behind the method call being authorized.
+pub struct PolkitAuthorizer {
+    authority: AuthorityProxy<'static>,
+}
+
+impl PolkitAuthorizer {
+    pub fn new() -> Result<Self> {
+        let connection = Connection::system().context("Failed to connect to the system bus for PolicyKit")?;
+        Self::with_connection(&connection)
+    }
+
+    /// Fails if no polkit authority is running or activatable, so the
+    /// privileged interface is never served without one
+    pub fn with_connection(connection: &Connection) -> Result<Self> {
+        let dbus = DBusProxy::new(connection)?;
+        let name = BusName::try_from(AUTHORITY_NAME)?;
+        let activatable = dbus.list_activatable_names()?.iter().any(|n| n.as_str() == AUTHORITY_NAME);
+        if !activatable && !dbus.name_has_owner(name)? {
+            anyhow::bail!("No PolicyKit authority ({}) on the system bus", AUTHORITY_NAME);
+        }
+
+        Ok(Self {
+            authority: zbus::block_on(AuthorityProxy::new(connection.inner()))?,
+        })
+    }
+}
+
+impl Authorizer for PolkitAuthorizer {
+    fn check<'a>(&'a self, sender: &'a str, action: Action) -> AuthorizationFuture<'a> {
+        Box::pin(async move {
+            let subject = ("system-bus-name", HashMap::from([("name", Value::from(sender))]));
+            let (authorized, challenge, _) = self
+                .authority
+                .check_authorization(&subject, action.id(), HashMap::new(), ALLOW_USER_INTERACTION, "")
+                .await
+                .with_context(|| format!("PolicyKit check for {} failed", action.id()))?;
+
+            log::debug!(
+                "PolicyKit {} for {}: authorized={} challenge={}",
+                action.id(), sender, authorized, challenge
+            );
+            Ok(authorized)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::dbus::TestBus;
+    use std::sync::{Arc, Mutex};
+    use zbus::blocking::connection;
+    use zbus::zvariant::OwnedValue;
+
+    /// Stands in for polkitd, granting only `setturbo`
+    struct FakeAuthority {
+        subjects: Arc<Mutex<Vec<(String, String)>>>,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.PolicyKit1.Authority")]
+    impl FakeAuthority {
+        fn check_authorization(
+            &self,
+            subject: (String, HashMap<String, OwnedValue>),
+            action_id: String,
+            _details: HashMap<String, String>,
+            flags: u32,
+            _cancellation_id: String,
+        ) -> (bool, bool, HashMap<String, String>) {
+            assert_eq!(flags, ALLOW_USER_INTERACTION);
+            let name: String = subject.1["name"].try_clone().unwrap().try_into().unwrap();
+            self.subjects.lock().unwrap().push((subject.0, name));
+            (action_id == Action::Turbo.id(), false, HashMap::new())
+        }
+    }
+
+    #[test]
+    fn test_polkit_authorizer_on_private_bus() {
+        let Some(bus) = TestBus::spawn() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+
+        // Without an authority the authorizer refuses to exist
+        let client = bus.connect();
+        assert!(PolkitAuthorizer::with_connection(&client).is_err());
+
+        let subjects = Arc::new(Mutex::new(vec![]));
+        let _polkitd = connection::Builder::address(bus.address.as_str())
+            .unwrap()
+            .name("org.freedesktop.PolicyKit1")
+            .unwrap()
+            .serve_at("/org/freedesktop/PolicyKit1/Authority", FakeAuthority { subjects: subjects.clone() })
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let authorizer = PolkitAuthorizer::with_connection(&client).unwrap();
+        assert!(zbus::block_on(authorizer.check(":1.42", Action::Turbo)).unwrap());
+        assert!(!zbus::block_on(authorizer.check(":1.42", Action::CpuFreq)).unwrap());
+        assert_eq!(subjects.lock().unwrap()[0], ("system-bus-name".to_string(), ":1.42".to_string()));
+    }
+}
@@ -0,0 +1,627 @@
+// power-profiles-daemon compatible D-Bus interface
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use zbus::blocking::{connection, fdo::DBusProxy, Connection};
+use zbus::message::Header;
+use zbus::names::BusName;
+use zbus::zvariant::Value;
+use zbus::fdo;
+
+use crate::config::{ConfigManager, PowerProfilesConfig};
+use crate::system::controller::Controller;
+use crate::system::events::{EventBus, ServiceEvent};
+use crate::system::polkit::{self, Action, Authorizer};
+
+pub const HADESS_BUS_NAME: &str = "net.hadess.PowerProfiles";
+pub const HADESS_PATH: &str = "/net/hadess/PowerProfiles";
+pub const UPOWER_BUS_NAME: &str = "org.freedesktop.UPower.PowerProfiles";
+pub const UPOWER_PATH: &str = "/org/freedesktop/UPower/PowerProfiles";
+
+/// The same interface is served under both the legacy and the current name
+const INTERFACES: [(&str, &str); 2] = [(HADESS_PATH, HADESS_BUS_NAME), (UPOWER_PATH, UPOWER_BUS_NAME)];
+
+const DRIVER: &str = "cpu-power-manager";
+
+/// The three modes desktops know about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerProfile {
+    PowerSaver,
+    Balanced,
+    Performance,
+}
+
+impl PowerProfile {
+    pub const ALL: [PowerProfile; 3] = [PowerProfile::PowerSaver, PowerProfile::Balanced, PowerProfile::Performance];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PowerProfile::PowerSaver => "power-saver",
+            PowerProfile::Balanced => "balanced",
+            PowerProfile::Performance => "performance",
+        }
+    }
+}
+
+impl FromStr for PowerProfile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        PowerProfile::ALL
+            .into_iter()
+            .find(|profile| profile.as_str() == s)
+            .ok_or_else(|| anyhow::anyhow!("Invalid power profile '{}'", s))
+    }
+}
+
+impl fmt::Display for PowerProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Hold {
+    profile: PowerProfile,
+    reason: String,
+    application_id: String,
+    /// Unique bus name of the holder; its holds go away when it disconnects
+    owner: String,
+}
+
+/// Selected mode, holds and the mapping onto our profiles, independent of D-Bus
+#[derive(Clone)]
+pub struct PowerProfilesState {
+    /// Config lookup name and profile display name for each mode
+    mapping: HashMap<&'static str, (String, String)>,
+    selected: PowerProfile,
+    holds: BTreeMap<u32, Hold>,
+    next_cookie: u32,
+}
+
+impl PowerProfilesState {
+    /// Starts in the mode of `active_profile`, the profile the service has applied,
+    /// and in balanced if that is none of the mapped ones
+    pub fn new(config: &PowerProfilesConfig, config_manager: &ConfigManager, active_profile: Option<&str>) -> Result<Self> {
+        let mut mapping = HashMap::new();
+        for (mode, name) in [
+            (PowerProfile::PowerSaver, &config.power_saver),
+            (PowerProfile::Balanced, &config.balanced),
+            (PowerProfile::Performance, &config.performance),
+        ] {
+            let profile = config_manager
+                .get_profile(name)
+                .with_context(|| format!("Invalid profile for power mode {}", mode))?;
+            mapping.insert(mode.as_str(), (name.clone(), profile.name));
+        }
+
+        let mut state = Self {
+            mapping,
+            selected: PowerProfile::Balanced,
+            holds: BTreeMap::new(),
+            next_cookie: 1,
+        };
+        if let Some(name) = active_profile {
+            state.profile_applied(name);
+        }
+        Ok(state)
+    }
+
+    /// Name to apply for a mode
+    pub fn profile_name(&self, mode: PowerProfile) -> &str {
+        &self.mapping[mode.as_str()].0
+    }
+
+    /// The mode in effect: holds win over the selection, power-saver holds over performance ones
+    pub fn active(&self) -> PowerProfile {
+        let mut active = None;
+        for hold in self.holds.values() {
+            if hold.profile == PowerProfile::PowerSaver {
+                return PowerProfile::PowerSaver;
+            }
+            active = Some(hold.profile);
+        }
+        active.unwrap_or(self.selected)
+    }
+
+    /// Select a mode; like power-profiles-daemon this drops all holds.
+    /// Returns the released cookies.
+    pub fn select(&mut self, mode: PowerProfile) -> Vec<u32> {
+        self.selected = mode;
+        let released = self.holds.keys().copied().collect();
+        self.holds.clear();
+        released
+    }
+
+    pub fn hold(&mut self, mode: PowerProfile, reason: &str, application_id: &str, owner: &str) -> Result<u32> {
+        if mode == PowerProfile::Balanced {
+            anyhow::bail!("Only power-saver and performance can be held");
+        }
+
+        let cookie = self.next_cookie;
+        self.next_cookie += 1;
+        self.holds.insert(cookie, Hold {
+            profile: mode,
+            reason: reason.to_string(),
+            application_id: application_id.to_string(),
+            owner: owner.to_string(),
+        });
+        log::info!("{} holds {} ({}), cookie {}", application_id, mode, reason, cookie);
+        Ok(cookie)
+    }
+
+    pub fn release(&mut self, cookie: u32) -> Result<()> {
+        self.holds
+            .remove(&cookie)
+            .map(|_| ())
+            .ok_or_else(|| anyhow::anyhow!("No hold with cookie {}", cookie))
+    }
+
+    /// Drop the holds of a client that left the bus
+    pub fn release_owner(&mut self, owner: &str) -> Vec<u32> {
+        let released: Vec<u32> = self
+            .holds
+            .iter()
+            .filter(|(_, hold)| hold.owner == owner)
+            .map(|(cookie, _)| *cookie)
+            .collect();
+        for cookie in &released {
+            self.holds.remove(cookie);
+        }
+        released
+    }
+
+    /// A profile was applied by the service. If it is one of ours but not the
+    /// mode we think is active (auto-tuning, CLI, GUI), treat it as a selection.
+    /// Returns `None` when nothing changed.
+    pub fn profile_applied(&mut self, name: &str) -> Option<Vec<u32>> {
+        let mode = PowerProfile::ALL.into_iter().find(|mode| {
+            let (key, display_name) = &self.mapping[mode.as_str()];
+            key.eq_ignore_ascii_case(name) || display_name.eq_ignore_ascii_case(name)
+        })?;
+
+        if mode == self.active() {
+            return None;
+        }
+        Some(self.select(mode))
+    }
+
+    fn holds_property(&self) -> Vec<HashMap<String, Value<'static>>> {
+        self.holds
+            .values()
+            .map(|hold| {
+                HashMap::from([
+                    ("Profile".to_string(), Value::from(hold.profile.as_str())),
+                    ("Reason".to_string(), Value::from(hold.reason.clone())),
+                    ("ApplicationId".to_string(), Value::from(hold.application_id.clone())),
+                ])
+            })
+            .collect()
+    }
+}
+
+/// What the signal thread should announce
+enum Notification {
+    Changed,
+    Released(u32),
+}
+
+/// Shared by both interface names
+struct PowerProfiles {
+    controller: Arc<dyn Controller>,
+    authorizer: Arc<dyn Authorizer>,
+    state: Arc<Mutex<PowerProfilesState>>,
+    notify: Sender<Notification>,
+}
+
+impl PowerProfiles {
+    /// Apply the active mode's profile if `change` moved it, then announce the change.
+    /// If the profile cannot be applied the change is undone.
+    fn update<T>(&self, change: impl FnOnce(&mut PowerProfilesState) -> Result<T>) -> fdo::Result<T> {
+        let mut state = self.state.lock().unwrap();
+        let saved = state.clone();
+        let before = state.active();
+        let result = change(&mut state).map_err(|e| fdo::Error::InvalidArgs(format!("{:#}", e)))?;
+
+        let after = state.active();
+        if after != before {
+            log::info!("Power mode: {}", after);
+            if let Err(e) = self.controller.apply_profile(state.profile_name(after)) {
+                *state = saved;
+                return Err(fdo::Error::Failed(format!("{:#}", e)));
+            }
+        }
+        let _ = self.notify.send(Notification::Changed);
+        Ok(result)
+    }
+
+    fn announce_released(&self, released: Vec<u32>) {
+        for cookie in released {
+            let _ = self.notify.send(Notification::Released(cookie));
+        }
+    }
+
+    fn active_profile(&self) -> String {
+        self.state.lock().unwrap().active().to_string()
+    }
+
+    async fn set_active_profile(&self, header: Option<&Header<'_>>, profile: &str) -> fdo::Result<()> {
+        let mode: PowerProfile = profile.parse().map_err(|e: anyhow::Error| fdo::Error::InvalidArgs(e.to_string()))?;
+        polkit::authorize(self.authorizer.as_ref(), header, Action::SwitchProfile).await?;
+        let released = self.update(|state| Ok(state.select(mode)))?;
+        self.announce_released(released);
+        Ok(())
+    }
+
+    fn profiles(&self) -> Vec<HashMap<String, Value<'static>>> {
+        PowerProfile::ALL
+            .into_iter()
+            .map(|mode| {
+                HashMap::from([
+                    ("Profile".to_string(), Value::from(mode.as_str())),
+                    ("Driver".to_string(), Value::from(DRIVER)),
+                    ("CpuDriver".to_string(), Value::from(DRIVER)),
+                ])
+            })
+            .collect()
+    }
+
+    fn active_profile_holds(&self) -> Vec<HashMap<String, Value<'static>>> {
+        self.state.lock().unwrap().holds_property()
+    }
+
+    async fn hold_profile(&self, header: &Header<'_>, profile: &str, reason: &str, application_id: &str) -> fdo::Result<u32> {
+        let mode: PowerProfile = profile.parse().map_err(|e: anyhow::Error| fdo::Error::InvalidArgs(e.to_string()))?;
+        polkit::authorize(self.authorizer.as_ref(), Some(header), Action::HoldProfile).await?;
+        let owner = header.sender().map(|sender| sender.to_string()).unwrap_or_default();
+        self.update(|state| state.hold(mode, reason, application_id, &owner))
+    }
+
+    fn release_profile(&self, cookie: u32) -> fdo::Result<()> {
+        self.update(|state| state.release(cookie))?;
+        self.announce_released(vec![cookie]);
+        Ok(())
+    }
+}
+
+macro_rules! power_profiles_interface {
+    ($ty:ident, $name:tt) => {
+        struct $ty(Arc<PowerProfiles>);
+
+        #[zbus::interface(name = $name)]
+        impl $ty {
+            #[zbus(property)]
+            fn active_profile(&self) -> String {
+                self.0.active_profile()
+            }
+
+            #[zbus(property)]
+            async fn set_active_profile(
+                &self,
+                profile: String,
+                #[zbus(header)] header: Option<Header<'_>>,
+            ) -> zbus::Result<()> {
+                self.0.set_active_profile(header.as_ref(), &profile).await.map_err(zbus::Error::from)
+            }
+
+            #[zbus(property)]
+            fn profiles(&self) -> Vec<HashMap<String, Value<'static>>> {
+                self.0.profiles()
+            }
+
+            #[zbus(property)]
+            fn active_profile_holds(&self) -> Vec<HashMap<String, Value<'static>>> {
+                self.0.active_profile_holds()
+            }
+
+            #[zbus(property)]
+            fn actions(&self) -> Vec<String> {
+                vec![]
+            }
+
+            #[zbus(property)]
+            fn performance_degraded(&self) -> String {
+                String::new()
+            }
+
+            #[zbus(property)]
+            fn performance_inhibited(&self) -> String {
+                String::new()
+            }
+
+            #[zbus(property)]
+            fn version(&self) -> String {
+                env!("CARGO_PKG_VERSION").to_string()
+            }
+
+            async fn hold_profile(
+                &self,
+                #[zbus(header)] header: Header<'_>,
+                profile: &str,
+                reason: &str,
+                application_id: &str,
+            ) -> fdo::Result<u32> {
+                self.0.hold_profile(&header, profile, reason, application_id).await
+            }
+
+            fn release_profile(&self, cookie: u32) -> fdo::Result<()> {
+                self.0.release_profile(cookie)
+            }
+        }
+    };
+}
+
+power_profiles_interface!(HadessInterface, "net.hadess.PowerProfiles");
+power_profiles_interface!(UPowerInterface, "org.freedesktop.UPower.PowerProfiles");
+
+/// Owns the power-profiles-daemon bus names for as long as it is alive
+pub struct PowerProfilesServer {
+    connection: Connection,
+}
+
+impl PowerProfilesServer {
+    /// Serve on the system bus, or on the bus at `address` (used by tests).
+    /// Fails if power-profiles-daemon itself is running.
+    pub fn start(
+        controller: Arc<dyn Controller>,
+        authorizer: Arc<dyn Authorizer>,
+        state: PowerProfilesState,
+        events: &EventBus,
+        address: Option<&str>,
+    ) -> Result<Self> {
+        let (notify, notifications) = mpsc::channel();
+        let state = Arc::new(Mutex::new(state));
+        let inner = Arc::new(PowerProfiles {
+            controller,
+            authorizer,
+            state: state.clone(),
+            notify: notify.clone(),
+        });
+
+        let builder = match address {
+            Some(address) => connection::Builder::address(address)?,
+            None => connection::Builder::system()?,
+        };
+        let connection = builder
+            .name(HADESS_BUS_NAME)?
+            .name(UPOWER_BUS_NAME)?
+            .serve_at(HADESS_PATH, HadessInterface(inner.clone()))?
+            .serve_at(UPOWER_PATH, UPowerInterface(inner))?
+            .build()
+            .context("Failed to register the power-profiles-daemon names; is power-profiles-daemon running?")?;
+
+        // Follow profile changes made by anything else
+        let receiver = events.subscribe();
+        let event_state = state.clone();
+        let event_notify = notify.clone();
+        std::thread::spawn(move || {
+            for event in receiver {
+                if let ServiceEvent::ProfileChanged { name } = event
+                    && let Some(released) = event_state.lock().unwrap().profile_applied(&name)
+                {
+                    for cookie in released {
+                        let _ = event_notify.send(Notification::Released(cookie));
+                    }
+                    let _ = event_notify.send(Notification::Changed);
+                }
+            }
+        });
+
+        // Drop holds of clients that disconnect without releasing them
+        let dbus = DBusProxy::new(&connection)?;
+        let owner_changes = dbus.receive_name_owner_changed()?;
+        let owner_state = state.clone();
+        let owner_notify = notify;
+        std::thread::spawn(move || {
+            for signal in owner_changes {
+                let Ok(args) = signal.args() else { continue };
+                if args.new_owner().is_some() {
+                    continue;
+                }
+                let released = owner_state.lock().unwrap().release_owner(args.name());
+                if !released.is_empty() {
+                    for cookie in released {
+                        let _ = owner_notify.send(Notification::Released(cookie));
+                    }
+                    let _ = owner_notify.send(Notification::Changed);
+                }
+            }
+        });
+
+        let signal_connection = connection.clone();
+        std::thread::spawn(move || {
+            for notification in notifications {
+                if let Err(e) = emit_notification(&signal_connection, &state, notification) {
+                    log::warn!("Failed to emit power profile signal: {}", e);
+                }
+            }
+        });
+
+        log::info!("Serving {} and {} on D-Bus", HADESS_BUS_NAME, UPOWER_BUS_NAME);
+        Ok(Self { connection })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+}
+
+fn emit_notification(
+    connection: &Connection,
+    state: &Mutex<PowerProfilesState>,
+    notification: Notification,
+) -> zbus::Result<()> {
+    let destination: Option<BusName> = None;
+    for (path, interface) in INTERFACES {
+        match notification {
+            Notification::Changed => {
+                let state = state.lock().unwrap();
+                let changed = HashMap::from([
+                    ("ActiveProfile", Value::from(state.active().as_str())),
+                    ("ActiveProfileHolds", Value::from(state.holds_property())),
+                ]);
+                let invalidated: Vec<&str> = vec![];
+                connection.emit_signal(
+                    destination.clone(),
+                    path,
+                    "org.freedesktop.DBus.Properties",
+                    "PropertiesChanged",
+                    &(interface, changed, invalidated),
+                )?;
+            }
+            Notification::Released(cookie) => {
+                connection.emit_signal(destination.clone(), path, interface, "ProfileReleased", &(cookie,))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::controller::StubController;
+    use crate::system::dbus::TestBus;
+    use crate::system::polkit::StubAuthorizer;
+    use zbus::blocking::proxy::Builder;
+    use zbus::proxy::CacheProperties;
+    use zbus::zvariant::OwnedValue;
+
+    fn upower_proxy(client: &Connection) -> zbus::blocking::Proxy<'_> {
+        Builder::new(client)
+            .destination(UPOWER_BUS_NAME).unwrap()
+            .path(UPOWER_PATH).unwrap()
+            .interface(UPOWER_BUS_NAME).unwrap()
+            .cache_properties(CacheProperties::No)
+            .build()
+            .unwrap()
+    }
+
+    fn state() -> PowerProfilesState {
+        let mapping = HashMap::from([
+            ("power-saver", ("powersave".to_string(), "Power Saver".to_string())),
+            ("balanced", ("balanced".to_string(), "Balanced".to_string())),
+            ("performance", ("performance".to_string(), "Performance".to_string())),
+        ]);
+        PowerProfilesState {
+            mapping,
+            selected: PowerProfile::Balanced,
+            holds: BTreeMap::new(),
+            next_cookie: 1,
+        }
+    }
+
+    #[test]
+    fn test_holds_override_selection() {
+        let mut state = state();
+        assert_eq!(state.active(), PowerProfile::Balanced);
+        assert!(state.hold(PowerProfile::Balanced, "", "app", ":1.1").is_err());
+
+        let performance = state.hold(PowerProfile::Performance, "compiling", "ide", ":1.1").unwrap();
+        assert_eq!(state.active(), PowerProfile::Performance);
+        assert_eq!(state.profile_name(state.active()), "performance");
+
+        // Power saver wins over performance
+        let power_saver = state.hold(PowerProfile::PowerSaver, "low battery", "shell", ":1.2").unwrap();
+        assert_eq!(state.active(), PowerProfile::PowerSaver);
+        state.release(power_saver).unwrap();
+        assert!(state.release(power_saver).is_err());
+        assert_eq!(state.active(), PowerProfile::Performance);
+
+        // Holder left the bus
+        assert_eq!(state.release_owner(":1.1"), vec![performance]);
+        assert_eq!(state.active(), PowerProfile::Balanced);
+
+        // Our own application of the active mode changes nothing; anything else is a selection
+        state.hold(PowerProfile::Performance, "game", "steam", ":1.3").unwrap();
+        assert_eq!(state.profile_applied("Performance"), None);
+        assert_eq!(state.profile_applied("silent"), None);
+        assert_eq!(state.profile_applied("Power Saver"), Some(vec![3]));
+        assert_eq!(state.active(), PowerProfile::PowerSaver);
+        assert!(state.holds.is_empty());
+    }
+
+    #[test]
+    fn test_power_profiles_on_private_bus() {
+        let Some(bus) = TestBus::spawn() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+
+        let controller = Arc::new(StubController::default());
+        let authorizer = StubAuthorizer::new(true);
+        let _server =
+            PowerProfilesServer::start(controller.clone(), authorizer.clone(), state(), &EventBus::new(), Some(&bus.address))
+                .unwrap();
+
+        let client = bus.connect();
+        let proxy = upower_proxy(&client);
+        assert_eq!(proxy.get_property::<String>("ActiveProfile").unwrap(), "balanced");
+        assert_eq!(proxy.get_property::<Vec<HashMap<String, OwnedValue>>>("Profiles").unwrap().len(), 3);
+
+        proxy.set_property("ActiveProfile", "performance").unwrap();
+        assert!(proxy.set_property("ActiveProfile", "turbo").is_err());
+
+        let cookie: u32 = proxy.call("HoldProfile", &("power-saver", "low battery", "test")).unwrap();
+        let holds: Vec<HashMap<String, OwnedValue>> = proxy.get_property("ActiveProfileHolds").unwrap();
+        assert_eq!(holds.len(), 1);
+        assert_eq!(proxy.get_property::<String>("ActiveProfile").unwrap(), "power-saver");
+
+        let _: () = proxy.call("ReleaseProfile", &(cookie,)).unwrap();
+        assert_eq!(proxy.get_property::<String>("ActiveProfile").unwrap(), "performance");
+        assert_eq!(
+            *controller.calls.lock().unwrap(),
+            vec!["apply_profile performance", "apply_profile powersave", "apply_profile performance"]
+        );
+        assert_eq!(*authorizer.checks.lock().unwrap(), vec![Action::SwitchProfile, Action::HoldProfile]);
+    }
+
+    #[test]
+    fn test_failed_switches_are_rolled_back() {
+        let Some(bus) = TestBus::spawn() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+
+        // Performance maps onto a profile the controller does not know
+        let mut state = state();
+        state.mapping.insert("performance", ("turbo".to_string(), "Turbo".to_string()));
+        let controller = Arc::new(StubController::default());
+        let _server =
+            PowerProfilesServer::start(controller.clone(), StubAuthorizer::new(true), state, &EventBus::new(), Some(&bus.address))
+                .unwrap();
+
+        let client = bus.connect();
+        let proxy = upower_proxy(&client);
+        assert!(proxy.set_property("ActiveProfile", "performance").is_err());
+        assert_eq!(proxy.get_property::<String>("ActiveProfile").unwrap(), "balanced");
+        assert!(proxy.call::<_, _, u32>("HoldProfile", &("performance", "game", "test")).is_err());
+        assert!(proxy.get_property::<Vec<HashMap<String, OwnedValue>>>("ActiveProfileHolds").unwrap().is_empty());
+        assert!(controller.calls.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_unauthorized_switches_are_denied() {
+        let Some(bus) = TestBus::spawn() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+
+        let controller = Arc::new(StubController::default());
+        let authorizer = StubAuthorizer::new(false);
+        let _server =
+            PowerProfilesServer::start(controller.clone(), authorizer.clone(), state(), &EventBus::new(), Some(&bus.address))
+                .unwrap();
+
+        let client = bus.connect();
+        let proxy = upower_proxy(&client);
+        assert!(proxy.set_property("ActiveProfile", "power-saver").is_err());
+        assert!(proxy.call::<_, _, u32>("HoldProfile", &("power-saver", "low battery", "test")).is_err());
+        assert_eq!(proxy.get_property::<String>("ActiveProfile").unwrap(), "balanced");
+        assert!(controller.calls.lock().unwrap().is_empty());
+        assert_eq!(*authorizer.checks.lock().unwrap(), vec![Action::SwitchProfile, Action::HoldProfile]);
+    }
+}
@@ -0,0 +1,343 @@
+// Metric recording and report module
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::system::monitor::Sample;
+use crate::utils::temperature::TemperatureUnit;
+
+/// Recording file format, chosen by extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordFormat {
+    Csv,
+    Jsonl,
+}
+
+impl RecordFormat {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => Ok(Self::Csv),
+            Some("jsonl") => Ok(Self::Jsonl),
+            _ => anyhow::bail!("Recording must be a .csv or .jsonl file: {}", path.display()),
+        }
+    }
+}
+
+/// Parse durations like `90`, `90s`, `10m` or `1h`
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let (number, seconds_per_unit) = match s.char_indices().last() {
+        Some((i, 's')) => (&s[..i], 1),
+        Some((i, 'm')) => (&s[..i], 60),
+        Some((i, 'h')) => (&s[..i], 3600),
+        _ => (s, 1),
+    };
+    let value: u64 = number.trim().parse().with_context(|| format!("Invalid duration: {}", s))?;
+    let seconds = value
+        .checked_mul(seconds_per_unit)
+        .with_context(|| format!("Duration too long: {}", s))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Appends samples to a recording, flushing each one so an interrupted
+/// recording stays readable
+pub struct Recorder {
+    writer: BufWriter<File>,
+    format: RecordFormat,
+    header_written: bool,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> Result<Self> {
+        let format = RecordFormat::from_path(path)?;
+        let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+            format,
+            header_written: false,
+        })
+    }
+
+    pub fn write(&mut self, sample: &Sample) -> Result<()> {
+        match self.format {
+            RecordFormat::Csv => {
+                if !self.header_written {
+                    writeln!(self.writer, "{}", sample.csv_header(true))?;
+                    self.header_written = true;
+                }
+                writeln!(self.writer, "{}", sample.csv_row(true))?;
+            }
+            RecordFormat::Jsonl => writeln!(self.writer, "{}", serde_json::to_string(sample)?)?,
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Read back a recording written by `Recorder`
+pub fn load(path: &Path) -> Result<Vec<Sample>> {
+    let format = RecordFormat::from_path(path)?;
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut lines = content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+
+    match format {
+        RecordFormat::Csv => {
+            let Some((_, header)) = lines.next() else {
+                return Ok(vec![]);
+            };
+            lines
+                .map(|(i, line)| Sample::from_csv(header, line).with_context(|| format!("Line {}", i + 1)))
+                .collect()
+        }
+        RecordFormat::Jsonl => lines
+            .map(|(i, line)| serde_json::from_str(line).with_context(|| format!("Line {}", i + 1)))
+            .collect(),
+    }
+}
+
+/// Distribution of one metric over a recording
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub min: f32,
+    pub average: f32,
+    pub p50: f32,
+    pub p95: f32,
+    pub max: f32,
+}
+
+impl Stats {
+    pub fn from_values(mut values: Vec<f32>) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f32::total_cmp);
+        // Nearest-rank percentile
+        let percentile = |p: f32| values[((p / 100.0 * values.len() as f32).ceil() as usize).clamp(1, values.len()) - 1];
+
+        Some(Self {
+            min: values[0],
+            average: values.iter().sum::<f32>() / values.len() as f32,
+            p50: percentile(50.0),
+            p95: percentile(95.0),
+            max: values[values.len() - 1],
+        })
+    }
+}
+
+/// Summary of a recording. Time-based figures credit each sample with the
+/// interval since the one before it.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub samples: usize,
+    pub duration: Duration,
+    pub frequency_mhz: Option<Stats>,
+    pub usage_percent: Option<Stats>,
+    pub cpu_temp_celsius: Option<Stats>,
+    pub package_watts: Option<Stats>,
+    pub temp_threshold_celsius: f32,
+    pub time_above_temp: Duration,
+    pub time_throttled: Duration,
+    pub throttle_events: usize,
+    /// Package energy in watt-hours, if power was recorded
+    pub energy_wh: Option<f64>,
+    pub time_per_profile: BTreeMap<String, Duration>,
+    /// Unit temperatures are displayed in
+    pub temperature_unit: TemperatureUnit,
+}
+
+impl Report {
+    pub fn from_samples(samples: &[Sample], temp_threshold_celsius: f32, temperature_unit: TemperatureUnit) -> Self {
+        let mut report = Self {
+            samples: samples.len(),
+            duration: Duration::ZERO,
+            frequency_mhz: Stats::from_values(samples.iter().map(|s| s.average_freq_mhz as f32).collect()),
+            usage_percent: Stats::from_values(samples.iter().filter_map(|s| s.usage_percent).collect()),
+            cpu_temp_celsius: Stats::from_values(samples.iter().filter_map(|s| s.cpu_temp_celsius).collect()),
+            package_watts: Stats::from_values(samples.iter().filter_map(|s| s.package_watts).collect()),
+            temp_threshold_celsius,
+            time_above_temp: Duration::ZERO,
+            time_throttled: Duration::ZERO,
+            throttle_events: samples.iter().map(|s| s.throttle_events).sum(),
+            energy_wh: None,
+            time_per_profile: BTreeMap::new(),
+            temperature_unit,
+        };
+
+        for pair in samples.windows(2) {
+            let (previous, sample) = (&pair[0], &pair[1]);
+            let Ok(interval) = (sample.timestamp - previous.timestamp).to_std() else {
+                continue;
+            };
+
+            report.duration += interval;
+            if sample.cpu_temp_celsius.is_some_and(|t| t > temp_threshold_celsius) {
+                report.time_above_temp += interval;
+            }
+            if sample.throttle_events > 0 {
+                report.time_throttled += interval;
+            }
+            if let Some(watts) = sample.package_watts {
+                *report.energy_wh.get_or_insert(0.0) += watts as f64 * interval.as_secs_f64() / 3600.0;
+            }
+            if let Some(profile) = &sample.profile {
+                *report.time_per_profile.entry(profile.clone()).or_default() += interval;
+            }
+        }
+        report
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Samples: {} over {}", self.samples, format_duration(self.duration))?;
+        writeln!(f)?;
+        writeln!(f, "{:<18} {:>9} {:>9} {:>9} {:>9} {:>9}", "METRIC", "MIN", "AVG", "P50", "P95", "MAX")?;
+        let unit = self.temperature_unit;
+        let temperature = self.cpu_temp_celsius.map(|s| Stats {
+            min: unit.convert(s.min),
+            average: unit.convert(s.average),
+            p50: unit.convert(s.p50),
+            p95: unit.convert(s.p95),
+            max: unit.convert(s.max),
+        });
+        let metrics = [
+            ("Frequency (MHz)".to_string(), self.frequency_mhz),
+            ("Usage (%)".to_string(), self.usage_percent),
+            (format!("Temperature ({})", unit.symbol().trim()), temperature),
+            ("Package (W)".to_string(), self.package_watts),
+        ];
+        for (name, stats) in metrics {
+            match stats {
+                Some(s) => writeln!(
+                    f,
+                    "{:<18} {:>9.1} {:>9.1} {:>9.1} {:>9.1} {:>9.1}",
+                    name, s.min, s.average, s.p50, s.p95, s.max
+                )?,
+                None => writeln!(f, "{:<18} {:>9}", name, "n/a")?,
+            }
+        }
+        writeln!(f)?;
+
+        let share = |time: Duration| {
+            if self.duration.is_zero() {
+                0.0
+            } else {
+                time.as_secs_f64() / self.duration.as_secs_f64() * 100.0
+            }
+        };
+        writeln!(
+            f,
+            "Above {}: {} ({:.1}%)",
+            unit.format(self.temp_threshold_celsius, 0),
+            format_duration(self.time_above_temp),
+            share(self.time_above_temp)
+        )?;
+        writeln!(
+            f,
+            "Throttled: {} ({:.1}%), {} events",
+            format_duration(self.time_throttled),
+            share(self.time_throttled),
+            self.throttle_events
+        )?;
+        match self.energy_wh {
+            Some(wh) => writeln!(f, "Package energy: {:.2} Wh ({:.0} J)", wh, wh * 3600.0)?,
+            None => writeln!(f, "Package energy: n/a")?,
+        }
+
+        for (profile, time) in &self.time_per_profile {
+            writeln!(f, "Profile {}: {} ({:.1}%)", profile, format_duration(*time), share(*time))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testing::TempDir;
+    use chrono::{Local, TimeZone};
+
+    fn sample(secs: i64, temp: f32, watts: f32, throttle_events: usize, profile: &str) -> Sample {
+        Sample {
+            timestamp: Local.timestamp_opt(1_700_000_000 + secs, 0).unwrap(),
+            profile: Some(profile.to_string()),
+            governor: "powersave".to_string(),
+            turbo: Some(true),
+            average_freq_mhz: 2000,
+            usage_percent: None,
+            cpu_temp_celsius: Some(temp),
+            package_watts: Some(watts),
+            throttle_events,
+            cores: vec![],
+        }
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("45s").unwrap(), Duration::from_secs(45));
+        assert_eq!(parse_duration("10m").unwrap(), Duration::from_secs(600));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+        assert!(parse_duration("10 minutes").is_err());
+        assert!(parse_duration("18446744073709551615h").is_err());
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = Stats::from_values((1..=20).map(|v| v as f32).collect()).unwrap();
+        assert_eq!(stats.min, 1.0);
+        assert_eq!(stats.average, 10.5);
+        assert_eq!(stats.p50, 10.0);
+        assert_eq!(stats.p95, 19.0);
+        assert_eq!(stats.max, 20.0);
+        assert!(Stats::from_values(vec![]).is_none());
+    }
+
+    #[test]
+    fn test_report() {
+        let samples = vec![
+            sample(0, 60.0, 10.0, 0, "balanced"),
+            sample(60, 85.0, 20.0, 2, "balanced"),
+            sample(120, 70.0, 10.0, 0, "performance"),
+        ];
+        let report = Report::from_samples(&samples, 80.0, TemperatureUnit::Celsius);
+
+        assert_eq!(report.duration, Duration::from_secs(120));
+        assert_eq!(report.time_above_temp, Duration::from_secs(60));
+        assert_eq!(report.time_throttled, Duration::from_secs(60));
+        assert_eq!(report.throttle_events, 2);
+        // 20 W and 10 W for a minute each
+        assert!((report.energy_wh.unwrap() - 0.5).abs() < 1e-9);
+        assert_eq!(report.time_per_profile["performance"], Duration::from_secs(60));
+        assert!(report.to_string().contains("Above 80°C: 0:01:00 (50.0%)"));
+    }
+
+    #[test]
+    fn test_recording_round_trip() {
+        let dir = TempDir::new("recording");
+        for extension in ["csv", "jsonl"] {
+            let path = dir.join(format!("recording.{}", extension));
+            let samples = vec![sample(0, 60.0, 10.0, 0, "balanced"), sample(1, 61.0, 11.0, 1, "balanced")];
+
+            let mut recorder = Recorder::create(&path).unwrap();
+            for s in &samples {
+                recorder.write(s).unwrap();
+            }
+            let loaded = load(&path).unwrap();
+
+            assert_eq!(loaded.len(), 2);
+            assert_eq!(loaded[1].throttle_events, 1);
+            assert_eq!(loaded[1].cpu_temp_celsius, Some(61.0));
+        }
+        assert!(Recorder::create(Path::new("/tmp/recording.txt")).is_err());
+    }
+}
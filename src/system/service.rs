@@ -0,0 +1,489 @@
+// Background service (daemon) module
+use anyhow::{Context, Result};
+use nix::sys::signal::{self, SigHandler, Signal};
+use nix::time::{clock_gettime, ClockId};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::backend::power::ChargeThresholds;
+use crate::backend::profile::{FirmwareControls, Profile};
+use crate::backend::fan::FanController;
+use crate::backend::thermal_protection::Intervention;
+use crate::backend::{AutoTuner, CpuManager, FanManager, PmQosManager, PowerManager, TemperatureController, ThermalManager, ThermalProtector, ThrottleMonitor};
+use crate::config::{ConfigManager, ThermalConfig};
+use crate::system::controller::{self, Controller};
+use crate::system::dbus::DbusServer;
+use crate::system::events::{EventBus, ServiceEvent};
+use crate::system::metrics::{MetricsExporter, MetricsSnapshot};
+use crate::system::polkit::PolkitAuthorizer;
+use crate::system::power_profiles::{PowerProfilesServer, PowerProfilesState};
+use crate::system::socket::SocketServer;
+use crate::system::status::StatusReport;
+use crate::system::uevent::UeventMonitor;
+
+/// How long to wait for a burst of power_supply uevents to settle
+const POWER_EVENT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Minimum gap between suspend-aware and monotonic clocks that counts as a resume
+const RESUME_DETECTION_GAP: Duration = Duration::from_secs(2);
+
+static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn handle_shutdown_signal(_: nix::libc::c_int) {
+    SHUTDOWN_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+pub struct Service {
+    cpu_manager: CpuManager,
+    power_manager: PowerManager,
+    thermal_manager: ThermalManager,
+    firmware: FirmwareControls,
+    thermal_protector: ThermalProtector,
+    temperature_controller: Option<TemperatureController>,
+    fan_controller: Option<FanController>,
+    throttle_monitor: ThrottleMonitor,
+    pm_qos: PmQosManager,
+    config_manager: ConfigManager,
+    auto_tuner: AutoTuner,
+    active_profile: Option<Profile>,
+    on_ac_power: Option<bool>,
+    events: EventBus,
+}
+
+impl Service {
+    pub fn new() -> Result<Self> {
+        let config_manager = ConfigManager::system()?;
+        let thermal_config = config_manager.get_config().thermal.clone();
+        let mut thermal_manager = ThermalManager::new()?;
+        thermal_manager.set_cpu_temp_source(thermal_config.cpu_temp_source.clone());
+        let fan_controller = Self::create_fan_controller(&thermal_config, &thermal_manager)?;
+
+        Ok(Self {
+            cpu_manager: CpuManager::new()?,
+            power_manager: PowerManager::new()?,
+            thermal_manager,
+            firmware: FirmwareControls::new(),
+            thermal_protector: ThermalProtector::new(thermal_config.clone()),
+            temperature_controller: None,
+            fan_controller,
+            throttle_monitor: ThrottleMonitor::new(),
+            pm_qos: PmQosManager::new()?,
+            config_manager,
+            auto_tuner: AutoTuner::new()?,
+            active_profile: None,
+            on_ac_power: None,
+            events: EventBus::new(),
+        })
+    }
+
+    pub fn run(mut self) -> Result<()> {
+        Self::install_signal_handlers()?;
+
+        // Apply the profile for the current power source before anyone can ask for it
+        self.apply_charge_thresholds();
+        self.check_power_source();
+
+        let interval = Duration::from_millis(self.config_manager.get_config().general.polling_interval_ms);
+        let service_config = self.config_manager.get_config().service.clone();
+        let power_profiles = service_config.power_profiles.enabled.then(|| {
+            let active_profile = self.active_profile().map(|profile| profile.name.as_str());
+            PowerProfilesState::new(&service_config.power_profiles, &self.config_manager, active_profile)
+        });
+        let events = self.events.clone();
+        let service = Arc::new(Mutex::new(self));
+        let controller: Arc<dyn Controller> = Arc::new(ServiceController(service.clone()));
+        log::info!("Background service started (polling every {:?})", interval);
+
+        // Both bus interfaces fail closed without PolicyKit
+        let authorizer = if service_config.dbus_enabled || power_profiles.is_some() {
+            match PolkitAuthorizer::new() {
+                Ok(authorizer) => Some(Arc::new(authorizer)),
+                Err(e) => {
+                    log::warn!("PolicyKit unavailable, not serving D-Bus interfaces: {:#}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let _dbus_server = if service_config.dbus_enabled
+            && let Some(authorizer) = &authorizer
+        {
+            match DbusServer::start(controller.clone(), authorizer.clone(), &events, None) {
+                Ok(server) => Some(server),
+                Err(e) => {
+                    log::warn!("D-Bus interface unavailable: {:#}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let _socket_server = if service_config.socket.enabled {
+            match SocketServer::start(controller.clone(), &events, &service_config.socket) {
+                Ok(server) => Some(server),
+                Err(e) => {
+                    log::warn!("Control socket unavailable: {:#}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let _power_profiles_server = match (power_profiles, authorizer) {
+            (Some(state), Some(authorizer)) => {
+                match state.and_then(|state| PowerProfilesServer::start(controller, authorizer, state, &events, None)) {
+                    Ok(server) => Some(server),
+                    Err(e) => {
+                        log::warn!("Power profiles interface unavailable: {:#}", e);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        let mut metrics = match MetricsExporter::start(&service_config.metrics) {
+            Ok(exporter) => exporter,
+            Err(e) => {
+                log::warn!("Metrics export unavailable: {:#}", e);
+                None
+            }
+        };
+
+        let monitor = match UeventMonitor::new() {
+            Ok(monitor) => Some(monitor),
+            Err(e) => {
+                log::warn!("Power source events unavailable, falling back to polling: {}", e);
+                None
+            }
+        };
+
+        let mut resume_detector = ResumeDetector::new();
+        let mut next_tick = Instant::now() + interval;
+        while !SHUTDOWN_REQUESTED.load(Ordering::SeqCst) {
+            let timeout = next_tick.saturating_duration_since(Instant::now());
+
+            // Only hold the lock while doing work so D-Bus calls are served between ticks
+            match &monitor {
+                Some(monitor) => {
+                    if Self::wait_for_power_event(monitor, timeout) {
+                        Self::debounce_power_events(monitor);
+                        service.lock().unwrap().check_power_source();
+                    }
+                }
+                None => std::thread::sleep(timeout),
+            }
+
+            if Instant::now() >= next_tick {
+                let mut service = service.lock().unwrap();
+                if resume_detector.resumed() {
+                    log::info!("System resumed from suspend");
+                    service.apply_charge_thresholds();
+                }
+                if monitor.is_none() {
+                    service.check_power_source();
+                }
+                service.check_temperature();
+                service.check_throttling();
+                if let Some(exporter) = metrics.as_mut() {
+                    service.export_metrics(exporter);
+                }
+                next_tick = Instant::now() + interval;
+            }
+        }
+
+        let mut service = service.lock().unwrap();
+        service.pm_qos.release();
+        if let Some(fan_controller) = service.fan_controller.as_mut() {
+            fan_controller.restore();
+        }
+        log::info!("Background service stopped");
+        Ok(())
+    }
+
+    /// Apply a profile and take over the state it needs held for as long as it is active.
+    pub fn apply_profile(&mut self, profile: Profile) -> Result<()> {
+        profile.apply(&self.cpu_manager, &self.firmware)?;
+        self.thermal_protector.reset();
+
+        self.temperature_controller = match profile.target_temp_celsius {
+            Some(target) => {
+                let gains = self.config_manager.get_config().thermal.pid.clone();
+                match TemperatureController::new(target, profile.max_freq_mhz, gains, &self.cpu_manager) {
+                    Ok(controller) => Some(controller),
+                    Err(e) => {
+                        log::warn!("Failed to start temperature control at {:.1}°C: {}", target, e);
+                        None
+                    }
+                }
+            }
+            None => None,
+        };
+
+        if let Some(fan_controller) = self.fan_controller.as_mut() {
+            let default_curve = &self.config_manager.get_config().thermal.fan_curve;
+            fan_controller.set_curve(profile.fan_curve.clone().unwrap_or_else(|| default_curve.clone()));
+        }
+
+        match profile.cpu_dma_latency_us {
+            Some(latency_us) => {
+                if let Err(e) = self.pm_qos.hold_cpu_dma_latency(latency_us) {
+                    log::warn!("Failed to hold CPU DMA latency at {} us: {}", latency_us, e);
+                }
+            }
+            None => self.pm_qos.release(),
+        }
+
+        self.events.publish(ServiceEvent::ProfileChanged { name: profile.name.clone() });
+        self.active_profile = Some(profile);
+        Ok(())
+    }
+
+    /// Apply a profile chosen by the user; auto-tuning leaves it in place
+    /// until the power source changes.
+    pub fn apply_manual_profile(&mut self, name: &str) -> Result<()> {
+        let profile = self.config_manager.get_profile(name)?;
+        self.apply_profile(profile)?;
+        self.auto_tuner.set_manual_override(name);
+        Ok(())
+    }
+
+    pub fn active_profile(&self) -> Option<&Profile> {
+        self.active_profile.as_ref()
+    }
+
+    fn wait_for_power_event(monitor: &UeventMonitor, timeout: Duration) -> bool {
+        match monitor.recv_timeout(timeout) {
+            Ok(Some(event)) => event.subsystem() == Some("power_supply"),
+            Ok(None) => false,
+            Err(e) => {
+                log::warn!("Failed to read uevent: {}", e);
+                std::thread::sleep(timeout);
+                false
+            }
+        }
+    }
+
+    /// Swallow the remaining events of a plug/unplug burst
+    fn debounce_power_events(monitor: &UeventMonitor) {
+        let deadline = Instant::now() + POWER_EVENT_DEBOUNCE;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() || monitor.recv_timeout(remaining).is_err() {
+                break;
+            }
+        }
+    }
+
+    fn check_power_source(&mut self) {
+        let on_ac = match self.power_manager.is_on_ac_power() {
+            Ok(on_ac) => on_ac,
+            Err(e) => {
+                log::debug!("Could not determine power source: {}", e);
+                return;
+            }
+        };
+
+        if self.on_ac_power != Some(on_ac) {
+            self.on_ac_power = Some(on_ac);
+            self.events.publish(ServiceEvent::PowerSourceChanged { on_ac });
+        }
+
+        if !self.config_manager.get_config().auto_tune.enabled {
+            return;
+        }
+
+        let Some(name) = self.auto_tuner.update_power_source(on_ac, &self.config_manager.get_config().auto_tune) else {
+            return;
+        };
+        log::info!("Power source: {}, switching to profile '{}'", if on_ac { "AC" } else { "battery" }, name);
+
+        let result = self.config_manager.get_profile(&name)
+            .and_then(|profile| self.apply_profile(profile));
+        if let Err(e) = result {
+            log::error!("Failed to apply profile '{}': {}", name, e);
+        }
+    }
+
+    fn check_temperature(&mut self) {
+        let temp = match self.thermal_manager.get_cpu_temperature() {
+            Ok(temp) => temp,
+            Err(e) => {
+                log::debug!("Could not read CPU temperature: {}", e);
+                return;
+            }
+        };
+
+        match self.thermal_protector.update(temp, &self.cpu_manager) {
+            Ok(Some(intervention)) => {
+                let unit = self.config_manager.get_config().general.temperature_unit;
+                self.events.publish(ServiceEvent::ThermalEvent {
+                    description: format!("Thermal protection at {}: {}", unit.format(temp, 1), intervention),
+                });
+                if let Intervention::Emergency(max_freq_mhz) = intervention {
+                    self.events.publish(ServiceEvent::ThermalEmergency { temp_celsius: temp, max_freq_mhz });
+                }
+            }
+            Ok(None) => {}
+            Err(e) => log::error!("Thermal protection failed: {}", e),
+        }
+
+        // Thermal protection takes precedence while it is holding the limits down
+        if !self.thermal_protector.is_active()
+            && let Some(controller) = self.temperature_controller.as_mut()
+            && let Err(e) = controller.update(temp, &self.cpu_manager)
+        {
+            log::error!("Temperature control failed: {}", e);
+        }
+
+        if let Some(fan_controller) = self.fan_controller.as_mut()
+            && let Err(e) = fan_controller.update(temp)
+        {
+            log::error!("Fan control failed: {}", e);
+        }
+    }
+
+    fn check_throttling(&mut self) {
+        for event in self.throttle_monitor.poll(&self.cpu_manager, &self.thermal_manager) {
+            self.events.publish(ServiceEvent::ThermalEvent {
+                description: format!("Throttling: {}", event.cause),
+            });
+        }
+    }
+
+    fn export_metrics(&self, exporter: &mut MetricsExporter) {
+        let snapshot = MetricsSnapshot {
+            cores: self.cpu_manager.get_all_core_status().unwrap_or_default(),
+            turbo: self.cpu_manager.is_turbo_enabled().ok(),
+            cpu_temp_celsius: self.thermal_manager.get_cpu_temperature().ok(),
+            thermal_zones: self.thermal_manager.get_all_zones(),
+            package_watts: exporter.sample_package_watts(),
+            active_profile: self.active_profile().map(|profile| profile.name.clone()),
+            on_ac_power: self.on_ac_power,
+            auto_tune_enabled: self.config_manager.get_config().auto_tune.enabled,
+            manual_override: self.auto_tuner.manual_override().map(String::from),
+            thermal_protection_active: self.thermal_protector.is_active(),
+            throttle_events: self.throttle_monitor.counts(),
+            kernel_throttle: self.throttle_monitor.kernel_totals(&self.cpu_manager),
+        };
+        exporter.publish(&snapshot);
+    }
+
+    fn create_fan_controller(thermal_config: &ThermalConfig, thermal_manager: &ThermalManager) -> Result<Option<FanController>> {
+        if !thermal_config.fan_control_enabled {
+            return Ok(None);
+        }
+
+        let mut manager = FanManager::new()?;
+        manager.select(&thermal_config.fans, thermal_manager.cpu_sensor_chip());
+        if manager.fans().is_empty() {
+            log::warn!("Fan control is enabled but no CPU fans were found; list them in thermal.fans");
+            return Ok(None);
+        }
+        let ids: Vec<String> = manager.fans().iter().map(|fan| fan.id()).collect();
+        log::info!("Fan control drives {}", ids.join(", "));
+
+        Ok(Some(FanController::new(
+            manager,
+            thermal_config.fan_curve.clone(),
+            thermal_config.fan_hysteresis_celsius,
+        )))
+    }
+
+    /// Reapply configured charge thresholds; firmware may reset them on boot or resume
+    fn apply_charge_thresholds(&self) {
+        let battery = &self.config_manager.get_config().battery;
+        let thresholds = ChargeThresholds {
+            start: battery.charge_start_threshold,
+            end: battery.charge_end_threshold,
+        };
+        if thresholds == ChargeThresholds::default() {
+            return;
+        }
+
+        if let Err(e) = self.power_manager.set_charge_thresholds_all(thresholds) {
+            log::warn!("Failed to apply charge thresholds: {}", e);
+        }
+    }
+
+    fn install_signal_handlers() -> Result<()> {
+        let handler = SigHandler::Handler(handle_shutdown_signal);
+        for sig in [Signal::SIGINT, Signal::SIGTERM] {
+            unsafe { signal::signal(sig, handler) }
+                .with_context(|| format!("Failed to install {} handler", sig))?;
+        }
+        Ok(())
+    }
+}
+
+/// Serves D-Bus requests from the running service, so changes made by
+/// clients go through the same state (active profile, thermal protection,
+/// fan curve) as automatic ones.
+pub struct ServiceController(Arc<Mutex<Service>>);
+
+impl Controller for ServiceController {
+    fn status(&self) -> Result<StatusReport> {
+        let service = self.0.lock().unwrap();
+        StatusReport::collect(
+            &service.cpu_manager,
+            &service.thermal_manager,
+            &service.power_manager,
+            service.active_profile().map(|profile| profile.name.clone()),
+        )
+    }
+
+    fn list_profiles(&self) -> Result<Vec<String>> {
+        Ok(self.0.lock().unwrap().config_manager.profile_names())
+    }
+
+    fn apply_profile(&self, name: &str) -> Result<()> {
+        log::info!("Applying profile '{}' on client request", name);
+        self.0.lock().unwrap().apply_manual_profile(name)
+    }
+
+    fn set_governor(&self, governor: &str) -> Result<()> {
+        self.0.lock().unwrap().cpu_manager.set_governor_all(governor)
+    }
+
+    fn set_turbo(&self, enabled: bool) -> Result<()> {
+        self.0.lock().unwrap().cpu_manager.set_turbo(enabled)
+    }
+
+    fn set_frequency_limits(&self, min_mhz: u32, max_mhz: u32) -> Result<()> {
+        controller::apply_frequency_limits(&self.0.lock().unwrap().cpu_manager, min_mhz, max_mhz)
+    }
+
+    fn set_core_online(&self, core: usize, online: bool) -> Result<()> {
+        self.0.lock().unwrap().cpu_manager.set_core_online(core, online)
+    }
+}
+
+/// Detects suspend/resume by comparing CLOCK_BOOTTIME, which keeps counting
+/// while suspended, against CLOCK_MONOTONIC, which does not.
+struct ResumeDetector {
+    last_gap: Option<Duration>,
+}
+
+impl ResumeDetector {
+    fn new() -> Self {
+        Self { last_gap: Self::clock_gap() }
+    }
+
+    fn clock_gap() -> Option<Duration> {
+        let boottime = Duration::from(clock_gettime(ClockId::CLOCK_BOOTTIME).ok()?);
+        let monotonic = Duration::from(clock_gettime(ClockId::CLOCK_MONOTONIC).ok()?);
+        Some(boottime.saturating_sub(monotonic))
+    }
+
+    fn resumed(&mut self) -> bool {
+        let gap = Self::clock_gap();
+        let resumed = matches!((self.last_gap, gap), (Some(last), Some(now)) if now.saturating_sub(last) >= RESUME_DETECTION_GAP);
+        self.last_gap = gap;
+        resumed
+    }
+}
@@ -0,0 +1,367 @@
+// Unix socket control module
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+
+use nix::sys::socket::{getsockopt, sockopt::PeerCredentials};
+
+use crate::config::SocketConfig;
+use crate::system::controller::Controller;
+use crate::system::events::{EventBus, ServiceEvent};
+use crate::system::status::StatusReport;
+
+/// One request per line, e.g. `{"command":"set-turbo","enabled":false}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum Request {
+    Status,
+    ListProfiles,
+    ApplyProfile { name: String },
+    SetGovernor { governor: String },
+    SetTurbo { enabled: bool },
+    SetFrequencyLimits { min_mhz: u32, max_mhz: u32 },
+    SetCoreOnline { core: usize, online: bool },
+    /// Acknowledged once, then every service event is sent as its own line
+    Subscribe,
+}
+
+/// Reply line: `{"ok":true,"result":...}` or `{"ok":false,"error":"..."}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Response {
+    fn from_result(result: Result<serde_json::Value>) -> Self {
+        match result {
+            Ok(value) => Self { ok: true, result: (!value.is_null()).then_some(value), error: None },
+            Err(e) => Self { ok: false, result: None, error: Some(format!("{:#}", e)) },
+        }
+    }
+}
+
+/// Whether a peer may use the socket. Root is always allowed.
+fn is_allowed(config: &SocketConfig, uid: u32, gids: &[u32]) -> bool {
+    uid == 0
+        || config.allowed_uids.contains(&uid)
+        || gids.iter().any(|gid| config.allowed_gids.contains(gid))
+}
+
+/// Supplementary groups of the peer as of `connect()`, from `SO_PEERGROUPS`.
+/// Unlike `/proc/<pid>/status` this cannot race with the pid being reused.
+fn peer_groups(stream: &UnixStream) -> Result<Vec<u32>> {
+    const GID_SIZE: usize = std::mem::size_of::<libc::gid_t>();
+    let mut groups: Vec<libc::gid_t> = vec![0; 32];
+    loop {
+        let mut len = (groups.len() * GID_SIZE) as libc::socklen_t;
+        // SAFETY: the kernel writes at most `len` bytes into `groups`
+        let ret = unsafe {
+            libc::getsockopt(
+                stream.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_PEERGROUPS,
+                groups.as_mut_ptr().cast(),
+                &mut len,
+            )
+        };
+        if ret == 0 {
+            groups.truncate(len as usize / GID_SIZE);
+            return Ok(groups);
+        }
+
+        // On ERANGE the kernel reports the size it needs
+        let error = std::io::Error::last_os_error();
+        if error.raw_os_error() == Some(libc::ERANGE) && len as usize > groups.len() * GID_SIZE {
+            groups.resize(len as usize / GID_SIZE, 0);
+            continue;
+        }
+        return Err(error).context("Failed to read peer groups");
+    }
+}
+
+fn write_line<T: Serialize>(stream: &mut UnixStream, value: &T) -> Result<()> {
+    let mut line = serde_json::to_string(value)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Run a request against the controller; settings changes reply with no result
+fn dispatch(controller: &dyn Controller, request: Request) -> Result<serde_json::Value> {
+    let null = |_| serde_json::Value::Null;
+    match request {
+        Request::Status => Ok(serde_json::to_value(controller.status()?)?),
+        Request::ListProfiles => Ok(serde_json::to_value(controller.list_profiles()?)?),
+        Request::ApplyProfile { name } => controller.apply_profile(&name).map(null),
+        Request::SetGovernor { governor } => controller.set_governor(&governor).map(null),
+        Request::SetTurbo { enabled } => controller.set_turbo(enabled).map(null),
+        Request::SetFrequencyLimits { min_mhz, max_mhz } => controller.set_frequency_limits(min_mhz, max_mhz).map(null),
+        Request::SetCoreOnline { core, online } => controller.set_core_online(core, online).map(null),
+        Request::Subscribe => unreachable!("subscriptions are handled by the connection loop"),
+    }
+}
+
+fn handle_client(mut stream: UnixStream, controller: &dyn Controller, events: &EventBus, config: &SocketConfig) -> Result<()> {
+    let credentials = getsockopt(&stream, PeerCredentials).context("Failed to read peer credentials")?;
+    let mut gids = peer_groups(&stream)?;
+    gids.push(credentials.gid());
+
+    if !is_allowed(config, credentials.uid(), &gids) {
+        log::warn!("Rejected socket client uid {} (pid {})", credentials.uid(), credentials.pid());
+        let denied = anyhow::anyhow!("uid {} is not allowed to use this socket", credentials.uid());
+        return write_line(&mut stream, &Response::from_result(Err(denied)));
+    }
+
+    let reader = BufReader::new(stream.try_clone()?);
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let request: Request = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                write_line(&mut stream, &Response::from_result(Err(anyhow::anyhow!("Invalid request: {}", e))))?;
+                continue;
+            }
+        };
+        log::debug!("Socket request from uid {}: {:?}", credentials.uid(), request);
+
+        if request == Request::Subscribe {
+            let receiver = events.subscribe();
+            write_line(&mut stream, &Response::from_result(Ok(serde_json::Value::Null)))?;
+            // Ends with an error once the client hangs up
+            for event in receiver {
+                write_line(&mut stream, &event)?;
+            }
+            return Ok(());
+        }
+
+        write_line(&mut stream, &Response::from_result(dispatch(controller, request)))?;
+    }
+    Ok(())
+}
+
+/// Listens on the control socket until dropped, then removes the socket file
+pub struct SocketServer {
+    path: PathBuf,
+}
+
+impl SocketServer {
+    pub fn start(controller: Arc<dyn Controller>, events: &EventBus, config: &SocketConfig) -> Result<Self> {
+        let path = PathBuf::from(&config.path);
+        // A previous instance that crashed leaves its socket file behind
+        if UnixStream::connect(&path).is_ok() {
+            anyhow::bail!("Another service is already listening on {}", path.display());
+        }
+        let _ = fs::remove_file(&path);
+        // systemd creates the runtime directory, but not when run by hand
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let listener = UnixListener::bind(&path).with_context(|| format!("Failed to bind {}", path.display()))?;
+        // Access is checked per connection against the allowlist
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o666))?;
+        log::info!("Listening for control requests on {}", path.display());
+
+        let events = events.clone();
+        let config = config.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        log::warn!("Failed to accept socket client: {}", e);
+                        continue;
+                    }
+                };
+                let controller = controller.clone();
+                let events = events.clone();
+                let config = config.clone();
+                thread::spawn(move || {
+                    if let Err(e) = handle_client(stream, controller.as_ref(), &events, &config) {
+                        log::debug!("Socket client disconnected: {:#}", e);
+                    }
+                });
+            }
+        });
+
+        Ok(Self { path })
+    }
+}
+
+impl Drop for SocketServer {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Client side of the control socket, used when D-Bus is unavailable
+pub struct SocketController {
+    path: PathBuf,
+}
+
+impl SocketController {
+    /// Returns `None` if no service is listening on `path` or it does not accept us
+    pub fn connect(path: impl AsRef<Path>) -> Result<Option<Self>> {
+        let path = path.as_ref();
+        let stream = match UnixStream::connect(path) {
+            Ok(stream) => stream,
+            Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::ConnectionRefused) => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Failed to connect to {}", path.display())),
+        };
+
+        // Callers not on the allowlist are only told so after their first request
+        let (response, _) = Self::exchange(stream, &Request::Status)?;
+        if !response.ok {
+            log::debug!(
+                "Service refused {}: {}",
+                path.display(),
+                response.error.as_deref().unwrap_or("request failed")
+            );
+            return Ok(None);
+        }
+        Ok(Some(Self { path: path.to_path_buf() }))
+    }
+
+    /// Write one request and read its reply line
+    fn exchange(mut stream: UnixStream, request: &Request) -> Result<(Response, BufReader<UnixStream>)> {
+        write_line(&mut stream, request)?;
+
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            anyhow::bail!("Service closed the connection");
+        }
+        let response = serde_json::from_str(&line).context("Failed to parse service response")?;
+        Ok((response, reader))
+    }
+
+    /// Send one request and check the reply, returning the reply's value and the connection
+    fn send(&self, request: &Request) -> Result<(Option<serde_json::Value>, BufReader<UnixStream>)> {
+        let stream = UnixStream::connect(&self.path)
+            .with_context(|| format!("Failed to connect to {}", self.path.display()))?;
+        let (response, reader) = Self::exchange(stream, request)?;
+        if !response.ok {
+            anyhow::bail!(response.error.unwrap_or_else(|| "Request failed".to_string()));
+        }
+        Ok((response.result, reader))
+    }
+
+    fn call(&self, request: Request) -> Result<()> {
+        self.send(&request).map(|_| ())
+    }
+
+    /// Stream service events until the service goes away
+    pub fn subscribe(&self) -> Result<impl Iterator<Item = Result<ServiceEvent>> + use<>> {
+        let (_, reader) = self.send(&Request::Subscribe)?;
+        Ok(reader.lines().map(|line| Ok(serde_json::from_str(&line?)?)))
+    }
+}
+
+impl Controller for SocketController {
+    fn status(&self) -> Result<StatusReport> {
+        let (result, _) = self.send(&Request::Status)?;
+        serde_json::from_value(result.unwrap_or_default()).context("Failed to parse service status")
+    }
+
+    fn list_profiles(&self) -> Result<Vec<String>> {
+        let (result, _) = self.send(&Request::ListProfiles)?;
+        serde_json::from_value(result.unwrap_or_default()).context("Failed to parse profile list")
+    }
+
+    fn apply_profile(&self, name: &str) -> Result<()> {
+        self.call(Request::ApplyProfile { name: name.to_string() })
+    }
+
+    fn set_governor(&self, governor: &str) -> Result<()> {
+        self.call(Request::SetGovernor { governor: governor.to_string() })
+    }
+
+    fn set_turbo(&self, enabled: bool) -> Result<()> {
+        self.call(Request::SetTurbo { enabled })
+    }
+
+    fn set_frequency_limits(&self, min_mhz: u32, max_mhz: u32) -> Result<()> {
+        self.call(Request::SetFrequencyLimits { min_mhz, max_mhz })
+    }
+
+    fn set_core_online(&self, core: usize, online: bool) -> Result<()> {
+        self.call(Request::SetCoreOnline { core, online })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system::controller::StubController;
+    use crate::utils::testing::TempDir;
+
+    #[test]
+    fn test_request_format() {
+        let request: Request = serde_json::from_str(r#"{"command":"set-turbo","enabled":false}"#).unwrap();
+        assert_eq!(request, Request::SetTurbo { enabled: false });
+        assert_eq!(serde_json::to_string(&Request::Status).unwrap(), r#"{"command":"status"}"#);
+    }
+
+    #[test]
+    fn test_allowlist() {
+        let config = SocketConfig { allowed_uids: vec![1000], allowed_gids: vec![27], ..SocketConfig::default() };
+        assert!(is_allowed(&config, 0, &[0]));
+        assert!(is_allowed(&config, 1000, &[1000]));
+        assert!(is_allowed(&config, 1001, &[1001, 27]));
+        assert!(!is_allowed(&config, 1001, &[1001]));
+    }
+
+    #[test]
+    fn test_peer_groups() {
+        let (stream, _peer) = UnixStream::pair().unwrap();
+        let mut groups = peer_groups(&stream).unwrap();
+        let mut expected: Vec<u32> = nix::unistd::getgroups().unwrap().into_iter().map(|gid| gid.as_raw()).collect();
+        groups.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(groups, expected);
+    }
+
+    #[test]
+    fn test_socket_round_trip() {
+        let dir = TempDir::new("socket");
+        let path = dir.join("control.sock");
+        let config = SocketConfig {
+            path: path.to_string_lossy().into_owned(),
+            allowed_uids: vec![nix::unistd::Uid::current().as_raw()],
+            ..SocketConfig::default()
+        };
+        let stub = Arc::new(StubController::default());
+        let events = EventBus::new();
+        let server = SocketServer::start(stub.clone(), &events, &config).unwrap();
+
+        let client = SocketController::connect(&path).unwrap().expect("server is listening");
+        assert_eq!(client.status().unwrap().governor, "powersave");
+        assert_eq!(client.list_profiles().unwrap(), StubController::PROFILES);
+        client.set_governor("performance").unwrap();
+        client.set_turbo(false).unwrap();
+        assert_eq!(client.apply_profile("turbo").unwrap_err().to_string(), "Profile 'turbo' not found");
+        assert_eq!(*stub.calls.lock().unwrap(), vec!["set_governor performance", "set_turbo false"]);
+
+        let mut subscription = client.subscribe().unwrap();
+        events.publish(ServiceEvent::PowerSourceChanged { on_ac: true });
+        assert_eq!(subscription.next().unwrap().unwrap(), ServiceEvent::PowerSourceChanged { on_ac: true });
+
+        drop(server);
+        assert!(SocketController::connect(&path).unwrap().is_none());
+    }
+}
@@ -0,0 +1,154 @@
+// Status reporting module
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::backend::cpu::{CoreStatus, CpuInfo};
+use crate::backend::power::BatteryInfo;
+use crate::backend::thermal::ThermalZone;
+use crate::backend::{CpuManager, PowerManager, ThermalManager};
+
+/// Snapshot of the settings and readings clients care about
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusReport {
+    pub active_profile: Option<String>,
+    pub governor: String,
+    pub turbo: bool,
+    pub average_freq_mhz: u32,
+    pub min_freq_mhz: u32,
+    pub max_freq_mhz: u32,
+    pub cpu_temp_celsius: Option<f32>,
+    pub on_ac_power: Option<bool>,
+}
+
+impl StatusReport {
+    pub fn collect(
+        cpu_manager: &CpuManager,
+        thermal_manager: &ThermalManager,
+        power_manager: &PowerManager,
+        active_profile: Option<String>,
+    ) -> Result<Self> {
+        // Offline cores have no frequency to read
+        let freqs: Vec<u32> = cpu_manager
+            .get_all_core_status()?
+            .into_iter()
+            .filter(|core| core.online)
+            .map(|core| core.current_freq)
+            .collect();
+
+        Ok(Self {
+            active_profile,
+            governor: cpu_manager.get_governor(0)?,
+            turbo: cpu_manager.is_turbo_enabled()?,
+            average_freq_mhz: freqs.iter().sum::<u32>() / freqs.len().max(1) as u32,
+            min_freq_mhz: cpu_manager.get_scaling_min_freq(0)?,
+            max_freq_mhz: cpu_manager.get_scaling_max_freq(0)?,
+            cpu_temp_celsius: thermal_manager.get_cpu_temperature().ok(),
+            on_ac_power: power_manager.is_on_ac_power().ok(),
+        })
+    }
+}
+
+/// Everything `status --format` reports, for scripts and monitoring
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemStatus {
+    pub turbo: Option<bool>,
+    /// Energy performance preference of core 0, where the driver supports it
+    pub epp: Option<String>,
+    pub cpu_temp_celsius: Option<f32>,
+    pub on_ac_power: Option<bool>,
+    pub cpu: CpuInfo,
+    pub cores: Vec<CoreStatus>,
+    pub thermal_zones: Vec<ThermalZone>,
+    pub batteries: Vec<BatteryInfo>,
+}
+
+impl SystemStatus {
+    pub fn collect(cpu_manager: &CpuManager, thermal_manager: &ThermalManager, power_manager: &PowerManager) -> Result<Self> {
+        Ok(Self {
+            turbo: cpu_manager.is_turbo_enabled().ok(),
+            epp: cpu_manager.get_epp(0).ok(),
+            cpu_temp_celsius: thermal_manager.get_cpu_temperature().ok(),
+            on_ac_power: power_manager.is_on_ac_power().ok(),
+            cpu: cpu_manager.get_cpu_info()?,
+            cores: cpu_manager.get_all_core_status()?,
+            thermal_zones: thermal_manager.get_all_zones(),
+            batteries: power_manager.get_batteries().unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::cpu::CpuDriver;
+    use crate::backend::thermal::TripPoint;
+    use crate::utils::testing::{write_cpus, TempDir};
+
+    fn sample() -> SystemStatus {
+        SystemStatus {
+            turbo: Some(true),
+            epp: None,
+            cpu_temp_celsius: Some(52.0),
+            on_ac_power: Some(false),
+            cpu: CpuInfo {
+                model: "Test CPU".to_string(),
+                vendor: "GenuineIntel".to_string(),
+                core_count: 1,
+                driver: CpuDriver::IntelPstate,
+                min_freq: 800,
+                max_freq: 4000,
+                available_governors: vec!["performance".to_string(), "powersave".to_string()],
+                scaling_available_frequencies: vec![],
+            },
+            cores: vec![CoreStatus {
+                core_id: 0,
+                current_freq: 2400,
+                min_freq: 800,
+                max_freq: 4000,
+                governor: "powersave".to_string(),
+                online: true,
+                usage_percent: 12.5,
+                temperature_celsius: None,
+            }],
+            thermal_zones: vec![ThermalZone {
+                id: 0,
+                type_name: "x86_pkg_temp".to_string(),
+                temp_celsius: 52.0,
+                policy: Some("step_wise".to_string()),
+                trip_points: vec![TripPoint { id: 0, temp_celsius: 100.0, trip_type: "critical".to_string() }],
+                bindings: vec![],
+            }],
+            batteries: vec![],
+        }
+    }
+
+    #[test]
+    fn test_system_status_serializes() {
+        let status = sample();
+
+        let toml = toml::to_string(&status).unwrap();
+        let parsed: SystemStatus = toml::from_str(&toml).unwrap();
+        assert_eq!(parsed.cores[0].governor, "powersave");
+        assert!(parsed.epp.is_none());
+
+        let json: serde_json::Value = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["cpu"]["driver"], "IntelPstate");
+        assert_eq!(json["thermal_zones"][0]["trip_points"][0]["trip_type"], "critical");
+    }
+
+    #[test]
+    fn test_collect_with_offline_core() {
+        let base = TempDir::new("status");
+        write_cpus(&base.join("cpu"), &[true, false, true]);
+        let cpu_manager = CpuManager::from_base(&base.join("cpu")).unwrap();
+        let thermal_manager = ThermalManager::from_base(&base).unwrap();
+        let power_manager = PowerManager::from_base(&base.join("power_supply"));
+
+        let status = SystemStatus::collect(&cpu_manager, &thermal_manager, &power_manager).unwrap();
+        assert_eq!(status.cores.iter().filter(|core| core.online).count(), 2);
+        assert!(!status.cores[1].online);
+
+        let report = StatusReport::collect(&cpu_manager, &thermal_manager, &power_manager, None).unwrap();
+        assert_eq!(report.average_freq_mhz, 2400);
+    }
+}
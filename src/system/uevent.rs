@@ -0,0 +1,110 @@
+// Kernel uevent (netlink) monitoring module
+use anyhow::{Context, Result};
+use nix::errno::Errno;
+use nix::sys::socket::{
+    bind, recv, setsockopt, socket, sockopt, AddressFamily, MsgFlags, NetlinkAddr, SockFlag,
+    SockProtocol, SockType,
+};
+use nix::sys::time::TimeVal;
+use std::collections::HashMap;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::time::Duration;
+
+/// Multicast group the kernel broadcasts uevents on (group 2 is udev's)
+const KERNEL_UEVENT_GROUP: u32 = 1;
+const UEVENT_BUFFER_SIZE: usize = 8192;
+
+#[derive(Debug, Clone)]
+pub struct Uevent {
+    pub action: String,
+    pub devpath: String,
+    pub properties: HashMap<String, String>,
+}
+
+impl Uevent {
+    /// Parse a kernel uevent: `action@devpath` followed by NUL-separated `KEY=value` pairs
+    pub fn parse(buf: &[u8]) -> Option<Self> {
+        let mut fields = buf.split(|&b| b == 0).filter(|f| !f.is_empty());
+
+        let header = std::str::from_utf8(fields.next()?).ok()?;
+        let (action, devpath) = header.split_once('@')?;
+
+        let properties = fields
+            .filter_map(|f| std::str::from_utf8(f).ok())
+            .filter_map(|f| f.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        Some(Self {
+            action: action.to_string(),
+            devpath: devpath.to_string(),
+            properties,
+        })
+    }
+
+    pub fn subsystem(&self) -> Option<&str> {
+        self.properties.get("SUBSYSTEM").map(|s| s.as_str())
+    }
+}
+
+/// Listens for kernel uevents on a `NETLINK_KOBJECT_UEVENT` socket
+pub struct UeventMonitor {
+    fd: OwnedFd,
+}
+
+impl UeventMonitor {
+    pub fn new() -> Result<Self> {
+        let fd = socket(
+            AddressFamily::Netlink,
+            SockType::Datagram,
+            SockFlag::SOCK_CLOEXEC,
+            SockProtocol::NetlinkKObjectUEvent,
+        )
+        .context("Failed to create uevent netlink socket")?;
+
+        bind(fd.as_raw_fd(), &NetlinkAddr::new(0, KERNEL_UEVENT_GROUP))
+            .context("Failed to bind uevent netlink socket")?;
+
+        log::debug!("Listening for kernel uevents");
+        Ok(Self { fd })
+    }
+
+    /// Wait up to `timeout` for the next uevent. Returns `None` on timeout.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Option<Uevent>> {
+        // A zero timeout would block forever
+        let timeout = timeout.max(Duration::from_millis(1));
+        let timeval = TimeVal::new(
+            timeout.as_secs() as libc::time_t,
+            timeout.subsec_micros() as libc::suseconds_t,
+        );
+        setsockopt(&self.fd, sockopt::ReceiveTimeout, &timeval)
+            .context("Failed to set uevent socket timeout")?;
+
+        let mut buf = [0u8; UEVENT_BUFFER_SIZE];
+        match recv(self.fd.as_raw_fd(), &mut buf, MsgFlags::empty()) {
+            Ok(len) => Ok(Uevent::parse(&buf[..len])),
+            Err(Errno::EAGAIN) | Err(Errno::EINTR) => Ok(None),
+            Err(e) => Err(e).context("Failed to receive uevent"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_power_supply_uevent() {
+        let msg = b"change@/devices/LNXSYSTM:00/LNXSYBUS:00/ACPI0003:00/power_supply/ADP1\0\
+                    ACTION=change\0DEVPATH=/devices/LNXSYSTM:00/LNXSYBUS:00/ACPI0003:00/power_supply/ADP1\0\
+                    SUBSYSTEM=power_supply\0POWER_SUPPLY_NAME=ADP1\0POWER_SUPPLY_ONLINE=0\0SEQNUM=4711\0";
+
+        let event = Uevent::parse(msg).unwrap();
+        assert_eq!(event.action, "change");
+        assert!(event.devpath.ends_with("/power_supply/ADP1"));
+        assert_eq!(event.subsystem(), Some("power_supply"));
+        assert_eq!(event.properties.get("POWER_SUPPLY_ONLINE").map(|s| s.as_str()), Some("0"));
+
+        assert!(Uevent::parse(b"libudev\0garbage").is_none());
+    }
+}
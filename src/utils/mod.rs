@@ -1,5 +1,8 @@
 // Utility modules
 pub mod error;
+pub mod temperature;
+#[cfg(test)]
+pub mod testing;
 
 use thiserror::Error;
 
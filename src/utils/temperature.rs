@@ -0,0 +1,137 @@
+// Temperature unit handling
+use anyhow::Result;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Unit temperatures are displayed in. Everything is stored and compared in Celsius.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum TemperatureUnit {
+    #[default]
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+}
+
+impl TemperatureUnit {
+    /// Convert a Celsius value into this unit
+    pub fn convert(self, celsius: f32) -> f32 {
+        match self {
+            TemperatureUnit::Celsius => celsius,
+            TemperatureUnit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+            TemperatureUnit::Kelvin => celsius + 273.15,
+        }
+    }
+
+    pub fn to_celsius(self, value: f32) -> f32 {
+        match self {
+            TemperatureUnit::Celsius => value,
+            TemperatureUnit::Fahrenheit => (value - 32.0) * 5.0 / 9.0,
+            TemperatureUnit::Kelvin => value - 273.15,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            TemperatureUnit::Celsius => "°C",
+            TemperatureUnit::Fahrenheit => "°F",
+            TemperatureUnit::Kelvin => " K",
+        }
+    }
+
+    /// Format a Celsius reading in this unit, e.g. `format(70.0, 1)` → "158.0°F"
+    pub fn format(self, celsius: f32, precision: usize) -> String {
+        format!("{:.*}{}", precision, self.convert(celsius), self.symbol())
+    }
+}
+
+impl FromStr for TemperatureUnit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().trim_start_matches('°').to_lowercase().as_str() {
+            "c" | "celsius" => Ok(TemperatureUnit::Celsius),
+            "f" | "fahrenheit" => Ok(TemperatureUnit::Fahrenheit),
+            "k" | "kelvin" => Ok(TemperatureUnit::Kelvin),
+            _ => anyhow::bail!("Invalid temperature unit '{}', expected celsius, fahrenheit or kelvin", s),
+        }
+    }
+}
+
+impl TryFrom<String> for TemperatureUnit {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for TemperatureUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemperatureUnit::Celsius => write!(f, "celsius"),
+            TemperatureUnit::Fahrenheit => write!(f, "fahrenheit"),
+            TemperatureUnit::Kelvin => write!(f, "kelvin"),
+        }
+    }
+}
+
+impl From<TemperatureUnit> for String {
+    fn from(unit: TemperatureUnit) -> Self {
+        unit.to_string()
+    }
+}
+
+/// Parse a temperature with an optional unit suffix ("80", "80C", "176F", "353.15 K")
+/// and return it in Celsius. A bare number is Celsius.
+pub fn parse_temperature(s: &str) -> Result<f32> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-' || c == '+'))
+        .unwrap_or(s.len());
+    let (value, unit) = s.split_at(split);
+
+    let value: f32 = value
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid temperature '{}'", s))?;
+    let unit = if unit.trim().is_empty() { TemperatureUnit::Celsius } else { unit.parse()? };
+
+    Ok(unit.to_celsius(value))
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawTemperature {
+    Number(f32),
+    Text(String),
+}
+
+/// serde helper for Celsius thresholds that may also be written with a unit, e.g. `"176F"`
+pub fn deserialize_celsius<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<f32, D::Error> {
+    match RawTemperature::deserialize(deserializer)? {
+        RawTemperature::Number(celsius) => Ok(celsius),
+        RawTemperature::Text(s) => parse_temperature(&s).map_err(serde::de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format_temperatures() {
+        assert_eq!(parse_temperature("80").unwrap(), 80.0);
+        assert_eq!(parse_temperature("176F").unwrap(), 80.0);
+        assert_eq!(parse_temperature("353.15 K").unwrap(), 80.0);
+        assert_eq!(parse_temperature("80°C").unwrap(), 80.0);
+        assert!(parse_temperature("80X").is_err());
+        assert!(parse_temperature("hot").is_err());
+
+        assert_eq!("Fahrenheit".parse::<TemperatureUnit>().unwrap(), TemperatureUnit::Fahrenheit);
+        assert!("rankine".parse::<TemperatureUnit>().is_err());
+
+        assert_eq!(TemperatureUnit::Fahrenheit.format(70.0, 1), "158.0°F");
+        assert_eq!(TemperatureUnit::Kelvin.format(0.0, 0), "273 K");
+    }
+}
@@ -0,0 +1,66 @@
+// Test fixtures
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Scratch directory unique to one test, removed when dropped, including
+/// when the test panics
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("cpm-{}-{}-{}", name, std::process::id(), id));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self { path }
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// cpufreq files for each core; offline cores have none, like the kernel's
+/// EBUSY for an inactive policy
+pub fn write_cpus(base: &Path, online: &[bool]) {
+    for (core, &online) in online.iter().enumerate() {
+        let dir = base.join(format!("cpu{}", core));
+        fs::create_dir_all(&dir).unwrap();
+        if core > 0 {
+            fs::write(dir.join("online"), if online { "1\n" } else { "0\n" }).unwrap();
+        }
+        if !online {
+            continue;
+        }
+
+        let cpufreq = dir.join("cpufreq");
+        fs::create_dir_all(&cpufreq).unwrap();
+        for (name, value) in [
+            ("scaling_cur_freq", "2400000"),
+            ("scaling_min_freq", "800000"),
+            ("scaling_max_freq", "4000000"),
+            ("cpuinfo_min_freq", "800000"),
+            ("cpuinfo_max_freq", "4000000"),
+            ("scaling_governor", "powersave"),
+            ("scaling_available_governors", "performance powersave"),
+        ] {
+            fs::write(cpufreq.join(name), format!("{}\n", value)).unwrap();
+        }
+    }
+}